@@ -1,6 +1,15 @@
 // Copyright (C) 2020-2026 Andy Kurnia.
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    prost_build::compile_protos(&["src/macondo.proto"], &["src/"])?;
+    let mut config = prost_build::Config::new();
+    // the http front-end speaks json.
+    config.message_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]");
+    config.message_attribute(".", "#[serde(default)]");
+    config.enum_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]");
+    config.field_attribute(
+        ".macondo.BotRequest.bot_type",
+        "#[serde(with = \"crate::http::bot_code\")]",
+    );
+    config.compile_protos(&["src/macondo.proto", "src/analysis.proto"], &["src/"])?;
     Ok(())
 }
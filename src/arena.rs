@@ -0,0 +1,298 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+/*
+  usage:
+    cargo run --release --bin arena -- CSW24 num tilt3 tilt4 [threads]
+    cargo run --release --bin arena -- CSW24 num hasty:A.klv2 hasty:B.klv2
+  a strategy is hasty, noleave, tilt1 to tilt5 or sim, optionally followed by
+  :klv2 file (the default is LEXICON.klv2). the games are english, with LEXICON.kwg.
+  sim is the simming bot; sim with settings, like
+  sim,candidates=20,plies=3,iterations=500:A.klv2, is the rollouts in sim.rs.
+*/
+
+use omgbot::{seed, sim, stats, strategy};
+use rand::prelude::*;
+use wolges::*;
+
+use std::str::FromStr;
+
+struct Player {
+    name: String,
+    strategy: strategy::Strategy,
+    klv: klv::Klv<kwg::Node22>,
+}
+
+impl Player {
+    fn parse(s: &str, lexicon: &str) -> error::Returns<Self> {
+        let (strategy_str, klv_path) = match s.split_once(':') {
+            Some((strategy_str, klv_path)) => (strategy_str, klv_path.to_string()),
+            None => (s, format!("{lexicon}.klv2")),
+        };
+        let strategy = strategy::Strategy::from_str(strategy_str)?;
+        if strategy.use_common_word {
+            wolges::return_error!(format!("{s}: common words are not supported here"));
+        }
+        let klv = match strategy.picker {
+            strategy::Picker::NoLeave => klv::Klv::from_bytes_alloc(klv::EMPTY_KLV_BYTES),
+            _ => klv::Klv::from_bytes_alloc(
+                &std::fs::read(&klv_path).map_err(|err| format!("{klv_path}: {err}"))?,
+            ),
+        };
+        Ok(Self {
+            name: s.to_string(),
+            strategy,
+            klv,
+        })
+    }
+}
+
+struct GameResult {
+    a_went_first: bool,
+    a_score: i32,
+    b_score: i32,
+}
+
+// Plays one game between the two strategies, seat 0 goes first.
+fn play_game<N: kwg::Node>(
+    game_config: &game_config::GameConfig,
+    kwg: &kwg::Kwg<N>,
+    strategies: [&Player; 2],
+    seed: u64,
+    game_state: &mut game_state::GameState,
+    move_generator: &mut movegen::KurniaMoveGenerator,
+) -> error::Returns<[i32; 2]> {
+    let mut rng = rand::rngs::ChaCha20Rng::seed_from_u64(seed);
+    // with the settings for the rollouts in sim.rs, if used instead.
+    let mut seats = strategies.map(|player| match player.strategy.picker {
+        strategy::Picker::Hasty | strategy::Picker::NoLeave => (
+            move_filter::GenMoves::Unfiltered,
+            move_picker::MovePicker::Hasty,
+            None,
+        ),
+        strategy::Picker::Tilt(bot_level) => (
+            move_filter::GenMoves::Tilt {
+                tilt: move_filter::Tilt::new(
+                    game_config,
+                    kwg,
+                    move_filter::Tilt::length_importances(),
+                ),
+                bot_level,
+            },
+            move_picker::MovePicker::Hasty,
+            None,
+        ),
+        strategy::Picker::Sim(None) => (
+            move_filter::GenMoves::Unfiltered,
+            move_picker::MovePicker::Simmer(move_picker::Simmer::new(
+                game_config,
+                kwg,
+                &player.klv,
+            )),
+            None,
+        ),
+        strategy::Picker::Sim(Some(sim_params)) => (
+            move_filter::GenMoves::Unfiltered,
+            move_picker::MovePicker::Hasty,
+            Some(sim_params),
+        ),
+    });
+    let mut final_scores = [0; 2];
+    game_state.reset_and_draw_tiles(game_config, &mut rng);
+    loop {
+        let turn = game_state.turn as usize;
+        let (move_filter, move_picker, option_sim_params) = &mut seats[turn];
+        if let move_filter::GenMoves::Tilt { tilt, bot_level } = move_filter {
+            tilt.tilt_by_rng(&mut rng, *bot_level);
+        }
+        let board_snapshot = &movegen::BoardSnapshot {
+            board_tiles: &game_state.board_tiles,
+            game_config,
+            kwg,
+            klv: &strategies[turn].klv,
+        };
+        match option_sim_params {
+            Some(sim_params) => {
+                let mut sim_state = game_state.clone();
+                sim::unsee_other_racks(&mut sim_state);
+                sim::pick_a_move(
+                    sim_params,
+                    &sim_state,
+                    game_config,
+                    kwg,
+                    &strategies[turn].klv,
+                    move_generator,
+                    None,
+                    &mut rng,
+                )?;
+            }
+            None => move_picker.pick_a_move(
+                move_filter,
+                move_generator,
+                board_snapshot,
+                game_state,
+                &game_state.current_player().rack,
+            ),
+        }
+        let play = &move_generator.plays[0].play; // assume at least there's always Pass
+        game_state.play(game_config, &mut rng, play)?;
+        match game_state.check_game_ended(game_config, &mut final_scores) {
+            game_state::CheckGameEnded::PlayedOut | game_state::CheckGameEnded::ZeroScores => {
+                return Ok(final_scores);
+            }
+            game_state::CheckGameEnded::NotEnded => {}
+        }
+        game_state.next_turn();
+    }
+}
+
+// Game i is played with the seed of pair i / 2, and strategy A goes first in
+// the even one, so both strategies start from the same racks.
+fn play_games<N: kwg::Node>(
+    game_config: &game_config::GameConfig,
+    kwg: &kwg::Kwg<N>,
+    strategies: [&Player; 2],
+    seeder: &seed::Seeder,
+    game_indexes: impl Iterator<Item = usize>,
+) -> error::Returns<Vec<GameResult>> {
+    let mut game_state = game_state::GameState::new(game_config);
+    let mut move_generator = movegen::KurniaMoveGenerator::new(game_config);
+    let mut results = Vec::new();
+    for game_idx in game_indexes {
+        let a_went_first = game_idx % 2 == 0;
+        let seats = if a_went_first {
+            strategies
+        } else {
+            [strategies[1], strategies[0]]
+        };
+        let seed = seeder.request_seed("arena", game_idx / 2);
+        let final_scores = play_game(
+            game_config,
+            kwg,
+            seats,
+            seed,
+            &mut game_state,
+            &mut move_generator,
+        )?;
+        let (a_score, b_score) = if a_went_first {
+            (final_scores[0], final_scores[1])
+        } else {
+            (final_scores[1], final_scores[0])
+        };
+        println!(
+            "game {} (seed {seed}): {} {a_score} - {b_score} {}",
+            game_idx + 1,
+            strategies[0].name,
+            strategies[1].name
+        );
+        results.push(GameResult {
+            a_went_first,
+            a_score,
+            b_score,
+        });
+    }
+    Ok(results)
+}
+
+fn report(strategies: [&Player; 2], results: &[GameResult]) {
+    let wins = results
+        .iter()
+        .map(|result| stats::win_share(result.a_score, result.b_score))
+        .collect::<Vec<_>>();
+    let spreads = results
+        .iter()
+        .map(|result| (result.a_score - result.b_score) as f64)
+        .collect::<Vec<_>>();
+    let (win_rate, win_rate_stderr) = stats::mean_and_stderr(&wins);
+    let (mean_spread, mean_spread_stderr) = stats::mean_and_stderr(&spreads);
+    let first_wins = results
+        .iter()
+        .zip(wins.iter())
+        .filter(|(result, _)| result.a_went_first)
+        .map(|(_, win)| win)
+        .sum::<f64>();
+    let num_first = results.iter().filter(|result| result.a_went_first).count();
+    let z = stats::Z_95;
+    println!(
+        "{} vs {}, {} games ({} with {} first)",
+        strategies[0].name,
+        strategies[1].name,
+        results.len(),
+        num_first,
+        strategies[0].name
+    );
+    println!(
+        "wins: {:.1} ({:.2}% +/- {:.2}%), {:.1} of them going first",
+        wins.iter().sum::<f64>(),
+        100.0 * win_rate,
+        100.0 * z * win_rate_stderr,
+        first_wins
+    );
+    println!(
+        "spread: {mean_spread:.2} +/- {:.2} per game",
+        z * mean_spread_stderr
+    );
+    println!(
+        "elo: {:.1} ({:.1} to {:.1})",
+        stats::elo_of(win_rate),
+        stats::elo_of(win_rate - z * win_rate_stderr),
+        stats::elo_of(win_rate + z * win_rate_stderr)
+    );
+}
+
+fn main() -> error::Returns<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() <= 4 {
+        println!(
+            "args: CSW24 num strategy_a strategy_b [threads]
+  num
+    number of games, half with each strategy going first
+  strategy
+    hasty, noleave, tilt1 to tilt5, or sim, optionally with :file.klv2
+  threads
+    default is the number of cpus"
+        );
+        return Ok(());
+    }
+    let lexicon = &args[1];
+    let num_games = usize::from_str(&args[2])?;
+    let strategy_a = Player::parse(&args[3], lexicon)?;
+    let strategy_b = Player::parse(&args[4], lexicon)?;
+    let num_threads = match args.get(5) {
+        Some(s) => usize::from_str(s)?,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    }
+    .max(1);
+    let seeder = seed::Seeder::from_env()?;
+    println!("master seed: {}", seeder.master_seed());
+
+    let game_config = game_config::make_english_game_config();
+    let kwg = kwg::Kwg::<kwg::Node22>::from_bytes_alloc(&std::fs::read(format!("{lexicon}.kwg"))?);
+    let strategies = [&strategy_a, &strategy_b];
+    let mut results = Vec::with_capacity(num_games);
+    std::thread::scope(|s| -> error::Returns<()> {
+        let handles = (0..num_threads)
+            .map(|thread_idx| {
+                let (game_config, kwg, seeder) = (&game_config, &kwg, &seeder);
+                s.spawn(move || {
+                    play_games(
+                        game_config,
+                        kwg,
+                        strategies,
+                        seeder,
+                        (thread_idx..num_games).step_by(num_threads),
+                    )
+                    .map_err(|err| err.to_string())
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            results.extend(handle.join().map_err(|_| "thread panicked")??);
+        }
+        Ok(())
+    })?;
+    if results.is_empty() {
+        return Ok(());
+    }
+    report(strategies, &results);
+    Ok(())
+}
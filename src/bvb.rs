@@ -3,115 +3,39 @@
 /*
   usage:
     RUST_LOG=debug cargo run --release \
-      --bin bvb -- http://127.0.0.1:8001 gametag userid num 2>&1 | tee output.log
-  userid = 0 or 1
+      --bin bvb -- http://127.0.0.1:8001 gametag userid num [options] 2>&1 | tee output.log
+  userid = 0 or 1, or both to play both seats
   num = number of games
+  options, each followed by its value:
+    --lexicon CSW21 (reads CSW21.kwg, or CSW21.kbwg with --kwg-node 24)
+    --leave english.klv
+    --language english
+    --variant classic (or super)
+    --kwg-node 22 (or 24)
+    --results results.csv (or results.json, with one json record per line)
+    --resume results.csv (appends, counting the games already there)
+    --sessions 1 (games played at the same time, each on its own connection)
+    --strategy hasty (or noleave, sim, tilt1 to tilt5, any of them +common)
+      sim,candidates=20,plies=3,iterations=500 sims with the rollouts in sim.rs
+    --other-strategy hasty (for userid 1 when playing both seats)
+    --common-word CEL.kwg (the words +common plays)
+    --gcg-dir gcgs (saves the gcg of each finished game there)
+  the gcg of each finished game is checked against the game as rebuilt here.
+  requests that fail are sent again after a while, and a game whose requests
+  keep failing, or are refused, is abandoned and counted as invalid, as is a
+  game where waitTurn keeps timing out.
 */
 
+mod gcg;
+mod results;
+
 use log::*;
+use omgbot::{encode, parse_rack, position, seed, sim, stats, strategy};
 use rand::prelude::*;
 use wolges::*;
 
-use std::fmt::Write;
 use std::str::FromStr;
 
-thread_local! {
-    static RNG: std::cell::RefCell<Box<dyn rand::Rng>> = std::cell::RefCell::new(Box::new(
-        rand::rngs::ChaCha20Rng::try_from_rng(&mut rand::rngs::SysRng).unwrap(),
-    ));
-}
-
-// TODO remove derive
-#[derive(Debug)]
-struct Coord {
-    down: bool,
-    lane: i8,
-    idx: i8,
-}
-
-fn parse_coord_token(coord: &str, dim: &matrix::Dim) -> Option<Coord> {
-    let b = coord.as_bytes();
-    let l1 = b
-        .iter()
-        .position(|c| !c.is_ascii_digit())
-        .unwrap_or(b.len());
-    let dig1 = if l1 != 0 {
-        i8::try_from(usize::from_str(unsafe { std::str::from_utf8_unchecked(&b[..l1]) }).ok()? - 1)
-            .ok()?
-    } else {
-        0
-    };
-    let b = &b[l1..];
-    let l2 = b
-        .iter()
-        .position(|c| !c.is_ascii_alphabetic())
-        .unwrap_or(b.len());
-    if l2 == 0 {
-        return None;
-    }
-    if l1 != 0 && l2 != b.len() {
-        return None;
-    }
-    let alp2 = i8::try_from(display::str_to_column_usize_ignore_case(&b[..l2])?).ok()?;
-    if alp2 >= dim.cols {
-        return None;
-    }
-    if l1 != 0 {
-        if dig1 >= dim.rows {
-            return None;
-        }
-        return Some(Coord {
-            down: false,
-            lane: dig1,
-            idx: alp2,
-        });
-    }
-    let b = &b[l2..];
-    let l3 = b
-        .iter()
-        .position(|c| !c.is_ascii_digit())
-        .unwrap_or(b.len());
-    if l3 != b.len() {
-        return None;
-    }
-    let dig3 = i8::try_from(usize::from_str(unsafe { std::str::from_utf8_unchecked(b) }).ok()? - 1)
-        .ok()?;
-    if dig3 >= dim.rows {
-        return None;
-    }
-    Some(Coord {
-        down: true,
-        lane: alp2,
-        idx: dig3,
-    })
-}
-
-// handles '.' and the equivalent of A-Z, a-z
-fn parse_played_tiles(
-    alphabet_reader: &alphabet::AlphabetReader,
-    s: &str,
-    v: &mut Vec<u8>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    v.clear();
-    if !s.is_empty() {
-        v.reserve(s.len());
-        let sb = s.as_bytes();
-        let mut ix = 0;
-        while ix < sb.len() {
-            if let Some((tile, end_ix)) = alphabet_reader.next_tile(sb, ix) {
-                v.push(tile);
-                ix = end_ix;
-            } else if sb[ix] == b'.' {
-                v.push(0);
-                ix += 1;
-            } else {
-                wolges::return_error!(format!("invalid tile after {v:?} in {s:?}"));
-            }
-        }
-    }
-    Ok(())
-}
-
 // the server previously used this
 #[inline(always)]
 fn from_lowercase_rack(alphabet: &alphabet::Alphabet, idx: u8) -> Option<&str> {
@@ -140,15 +64,6 @@ fn new_for_lowercase_racks(alphabet: &alphabet::Alphabet) -> alphabet::AlphabetR
     alphabet::AlphabetReader::new_for_tiles(supported_tiles)
 }
 
-// handles the equivalent of '?', A-Z
-fn parse_rack(
-    alphabet_reader: &alphabet::AlphabetReader,
-    s: &str,
-    v: &mut Vec<u8>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    alphabet_reader.set_word(s, v)
-}
-
 fn check_ok(resp: reqwest::blocking::Response) -> error::Returns<reqwest::blocking::Response> {
     let status = resp.status();
     if status.is_success() {
@@ -161,6 +76,122 @@ fn check_ok(resp: reqwest::blocking::Response) -> error::Returns<reqwest::blocki
     }
 }
 
+const NUM_RETRIES: u32 = 5;
+const FIRST_BACKOFF: std::time::Duration = std::time::Duration::from_millis(500);
+const MAX_BACKOFF: std::time::Duration = std::time::Duration::from_secs(30);
+const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+// waitTurn only returns when it is our turn or the game is over, which may take
+// a while. It is sent again each time this runs out, up to MAX_WAIT_TURN_TIMEOUTS
+// times in a row.
+const WAIT_TURN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+const MAX_WAIT_TURN_TIMEOUTS: u32 = 10;
+
+// A game given up on because the server or the connection kept failing, or the
+// server refused a request about it. It is counted as invalid instead of
+// stopping the run.
+#[derive(Debug)]
+struct Abandoned {
+    gameid: String,
+    reason: String,
+}
+
+impl std::fmt::Display for Abandoned {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "game {} abandoned: {}", self.gameid, self.reason)
+    }
+}
+
+impl std::error::Error for Abandoned {}
+
+// for the errors text_of does not retry, from a request about one game.
+fn abandon_on_error<T>(gameid: &str, what: &str, result: error::Returns<T>) -> error::Returns<T> {
+    result.map_err(|err| -> Box<dyn std::error::Error> {
+        Box::new(Abandoned {
+            gameid: gameid.to_string(),
+            reason: format!("{what}: {err}"),
+        })
+    })
+}
+
+// Ok(Err(reason)) if the request may work when sent again: the connection
+// failed, it timed out, or the server had a hiccup (5xx). Other failures (4xx)
+// are errors.
+fn text_of(
+    result: reqwest::Result<reqwest::blocking::Response>,
+) -> error::Returns<Result<String, String>> {
+    match result {
+        Ok(resp) if resp.status().is_server_error() => {
+            let status = resp.status();
+            Ok(Err(match resp.text() {
+                Ok(t) => format!("{status:?}: {t}"),
+                Err(e) => format!("{status:?}: {e}"),
+            }))
+        }
+        Ok(resp) => Ok(check_ok(resp)?.text().map_err(|err| err.to_string())),
+        Err(err) => Ok(Err(err.to_string())),
+    }
+}
+
+struct Backoff {
+    num_failures: u32,
+    delay: std::time::Duration,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self {
+            num_failures: 0,
+            delay: FIRST_BACKOFF,
+        }
+    }
+
+    // sleeps before the next try, or gives up on the game.
+    fn wait(
+        &mut self,
+        log: &mut GameLog,
+        gameid: &str,
+        what: &str,
+        reason: String,
+    ) -> error::Returns<()> {
+        self.num_failures += 1;
+        if self.num_failures > NUM_RETRIES {
+            return Err(Box::new(Abandoned {
+                gameid: gameid.to_string(),
+                reason: format!("{what}: {reason}"),
+            }));
+        }
+        log_warn!(
+            log,
+            "{what} failed ({reason}), trying again in {:?}",
+            self.delay
+        );
+        std::thread::sleep(self.delay);
+        self.delay = (self.delay * 2).min(MAX_BACKOFF);
+        Ok(())
+    }
+}
+
+// For requests that are safe to send more than once.
+fn send_idempotent(
+    log: &mut GameLog,
+    gameid: &str,
+    what: &str,
+    make_request: impl Fn() -> reqwest::blocking::RequestBuilder,
+) -> error::Returns<String> {
+    let mut backoff = Backoff::new();
+    loop {
+        match abandon_on_error(
+            gameid,
+            what,
+            text_of(make_request().timeout(REQUEST_TIMEOUT).send()),
+        )? {
+            Ok(text) => return Ok(text),
+            Err(reason) => backoff.wait(log, gameid, what, reason)?,
+        }
+    }
+}
+
 fn do_get(
     client: &reqwest::blocking::Client,
     url: &str,
@@ -179,405 +210,824 @@ fn show_all(client: &reqwest::blocking::Client, url: &str, gameid: &str) -> erro
     Ok(())
 }
 
-fn do_it<N: kwg::Node + Sync>(
-    url: &str,
-    gametag: &str,
-    userid: &str,
-    num_games: usize,
-) -> error::Returns<()> {
-    let mut total_win = 0.0f64;
-    let mut total_loss = 0.0f64;
-    let mut total_spread = 0i64;
-    let mut num_completed = 0usize;
-    while num_completed < num_games {
-        // for nostalgic reasons, this uses CSW21.kwg with v1 english.klv
-        let klv = klv::Klv::<kwg::Node22>::from_bytes_alloc(&std::fs::read("english.klv")?);
-        let game_config = game_config::make_english_game_config();
-        let dim = game_config.board_layout().dim();
-        let alphabet = game_config.alphabet();
-        //let rack_reader = new_for_lowercase_racks(alphabet);
-        let rack_reader = alphabet::AlphabetReader::new_for_racks(alphabet);
-        let play_reader = alphabet::AlphabetReader::new_for_plays(alphabet);
-        let kwg = kwg::Kwg::<N>::from_bytes_alloc(&std::fs::read("CSW21.kwg")?);
-        let alphabet_len_without_blank = alphabet.len() - 1;
-        let mut available_tally_buf = Vec::new();
-        let mut game_state = game_state::GameState::new(&game_config);
-        let mut move_generator = movegen::KurniaMoveGenerator::new(&game_config);
-        let mut move_filter = move_filter::GenMoves::Unfiltered;
-        let mut move_picker = move_picker::MovePicker::Hasty;
-        let mut move_to_send_buf = String::new();
-
-        let mut place_tiles_buf = Vec::new();
-        let mut place_tiles = |board_tiles: &mut [u8],
-                               event: &str,
-                               kwg: Option<&kwg::Kwg<N>>|
-         -> Result<bool, Box<dyn std::error::Error>> {
-            // event is of the format 9I:SO.UwU
-
-            let (coord_token, played_tiles_token) =
-                event.split_once(':').ok_or(format!("no : in {event:?}"))?;
-            let coord =
-                parse_coord_token(coord_token, dim).ok_or(format!("invalid coord in {event:?}"))?;
-            let (strider, lane, idx) = (dim.lane(coord.down, coord.lane), coord.lane, coord.idx);
-            parse_played_tiles(&play_reader, played_tiles_token, &mut place_tiles_buf)?;
-            // note: not checking if first move covers star or if it connects
-            if place_tiles_buf.len() < 2 || !place_tiles_buf.iter().any(|&t| t != 0) {
-                wolges::return_error!("not enough tiles played".into());
-            }
-            if idx > 0 && board_tiles[strider.at(idx - 1)] != 0 {
-                wolges::return_error!("has prefix".into());
-            }
-            let end_idx = idx as usize + place_tiles_buf.len();
-            match end_idx.cmp(&(strider.len() as usize)) {
-                std::cmp::Ordering::Greater => {
-                    wolges::return_error!("out of bounds".into());
-                }
-                std::cmp::Ordering::Less => {
-                    if board_tiles[strider.at(end_idx as i8)] != 0 {
-                        wolges::return_error!("has suffix".into());
+// for nostalgic reasons, the defaults are CSW21.kwg with v1 english.klv
+struct Options {
+    lexicon: String,
+    leave: String,
+    language: String,
+    variant: String,
+    kwg_node: u8,
+    // with whether to resume.
+    option_results: Option<(String, bool)>,
+    num_sessions: usize,
+    strategy: strategy::Strategy,
+    other_strategy: strategy::Strategy,
+    option_common_word: Option<String>,
+    option_gcg_dir: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> error::Returns<Self> {
+        let mut options = Self {
+            lexicon: "CSW21".into(),
+            leave: "english.klv".into(),
+            language: "english".into(),
+            variant: "classic".into(),
+            kwg_node: 22,
+            option_results: None,
+            num_sessions: 1,
+            strategy: strategy::Strategy::from_str("hasty")?,
+            other_strategy: strategy::Strategy::from_str("hasty")?,
+            option_common_word: None,
+            option_gcg_dir: None,
+        };
+        let mut args = args.iter();
+        while let Some(option) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("no value for {option}"))?;
+            match option.as_str() {
+                "--lexicon" => options.lexicon.clone_from(value),
+                "--leave" => options.leave.clone_from(value),
+                "--language" => options.language.clone_from(value),
+                "--variant" => options.variant.clone_from(value),
+                "--kwg-node" => {
+                    options.kwg_node = match value.as_str() {
+                        "22" | "24" => u8::from_str(value)?,
+                        _ => wolges::return_error!(format!("bad --kwg-node {value}")),
                     }
                 }
-                std::cmp::Ordering::Equal => {}
-            }
-            for (i, &tile) in (idx..).zip(place_tiles_buf.iter()) {
-                let j = strider.at(i);
-                if tile == 0 {
-                    if board_tiles[j] == 0 {
-                        wolges::return_error!("playing through vacant board".into());
+                "--results" => options.option_results = Some((value.clone(), false)),
+                "--resume" => options.option_results = Some((value.clone(), true)),
+                "--sessions" => {
+                    options.num_sessions = usize::from_str(value)?;
+                    if options.num_sessions == 0 {
+                        wolges::return_error!("--sessions must be at least 1".to_string());
                     }
-                } else if board_tiles[j] != 0 {
-                    wolges::return_error!("board not vacant for non-played-through tile".into());
-                } else {
-                    board_tiles[j] = tile;
                 }
+                "--strategy" => options.strategy = strategy::Strategy::from_str(value)?,
+                "--other-strategy" => options.other_strategy = strategy::Strategy::from_str(value)?,
+                "--common-word" => options.option_common_word = Some(value.clone()),
+                "--gcg-dir" => options.option_gcg_dir = Some(value.clone()),
+                _ => wolges::return_error!(format!("unknown option {option}")),
             }
-            if let Some(kwg) = kwg {
-                let mut p_main = 0; // dawg
-                for (i, &tile) in (idx..).zip(place_tiles_buf.iter()) {
-                    let b = board_tiles[strider.at(i)];
-                    p_main = kwg.seek(p_main, b & 0x7f);
-                    if tile != 0 {
-                        let perpendicular_strider = dim.lane(!coord.down, i);
-                        let mut j = lane;
-                        while j > 0 && board_tiles[perpendicular_strider.at(j - 1)] != 0 {
-                            j -= 1;
-                        }
-                        let perpendicular_strider_len = perpendicular_strider.len();
-                        if j < lane
-                            || (j + 1 < perpendicular_strider_len
-                                && board_tiles[perpendicular_strider.at(j + 1)] != 0)
-                        {
-                            let mut p_perpendicular = 0;
-                            for j in j..perpendicular_strider_len {
-                                let perpendicular_tile = board_tiles[perpendicular_strider.at(j)];
-                                if perpendicular_tile == 0 {
-                                    break;
-                                }
-                                p_perpendicular =
-                                    kwg.seek(p_perpendicular, perpendicular_tile & 0x7f);
-                            }
-                            if p_perpendicular < 0 || !kwg[p_perpendicular].accepts() {
-                                return Ok(false);
-                            }
-                        }
-                    }
-                }
-                if p_main < 0 || !kwg[p_main].accepts() {
-                    return Ok(false);
-                }
+        }
+        if (options.strategy.use_common_word || options.other_strategy.use_common_word)
+            && options.option_common_word.is_none()
+        {
+            wolges::return_error!("+common needs --common-word".to_string());
+        }
+        Ok(options)
+    }
+
+    fn game_config(&self) -> error::Returns<game_config::GameConfig> {
+        Ok(match (self.language.as_str(), self.variant.as_str()) {
+            ("catalan", "classic") => game_config::make_catalan_game_config(),
+            ("catalan", "super") => game_config::make_super_catalan_game_config(),
+            ("english", "classic") => game_config::make_english_game_config(),
+            ("english", "super") => game_config::make_super_english_game_config(),
+            ("french", "classic") => game_config::make_french_game_config(),
+            ("german", "classic") => game_config::make_german_game_config(),
+            ("norwegian", "classic") => game_config::make_norwegian_game_config(),
+            ("polish", "classic") => game_config::make_polish_game_config(),
+            ("spanish", "classic") => game_config::make_spanish_game_config(),
+            (language, variant) => {
+                wolges::return_error!(format!("unsupported {language} {variant}"))
             }
-            Ok(true)
-        };
+        })
+    }
 
-        let client = reqwest::blocking::Client::builder().timeout(None).build()?;
+    fn kwg_path(&self) -> String {
+        match self.kwg_node {
+            24 => format!("{}.kbwg", self.lexicon),
+            _ => format!("{}.kwg", self.lexicon),
+        }
+    }
+}
 
-        let gameid = check_ok(
-            client
-                .post(format!("{url}/games"))
-                .basic_auth(userid, None::<&str>)
-                .body(gametag.to_string())
-                .send()?,
-        )?
-        .text()?;
-        if gameid.is_empty() {
-            return Err("no gameid".into());
-        }
-        info!("gameid={gameid}");
-        // assume no need to encode the gameid
-
-        let mut rack = Vec::new();
-        loop {
-            if false {
-                show_all(&client, url, &gameid)?; // anyone's turn
+fn read_file(path: &str) -> error::Returns<Vec<u8>> {
+    Ok(std::fs::read(path).map_err(|err| format!("{path}: {err}"))?)
+}
+
+// loaded once per run, shared by all the games.
+struct Loaded<N: kwg::Node> {
+    game_config: std::sync::Arc<game_config::GameConfig>,
+    kwg: kwg::Kwg<N>,
+    klv: klv::Klv<kwg::Node22>,
+    noleave_klv: klv::Klv<kwg::Node22>,
+    option_common_word_kwg: Option<kwg::Kwg<N>>,
+}
+
+impl<N: kwg::Node> Loaded<N> {
+    fn load(options: &Options) -> error::Returns<Self> {
+        Ok(Self {
+            game_config: std::sync::Arc::new(options.game_config()?),
+            kwg: kwg::Kwg::from_bytes_alloc(&read_file(&options.kwg_path())?),
+            klv: klv::Klv::from_bytes_alloc(&read_file(&options.leave)?),
+            noleave_klv: klv::Klv::from_bytes_alloc(klv::EMPTY_KLV_BYTES),
+            option_common_word_kwg: match &options.option_common_word {
+                Some(path) => Some(kwg::Kwg::from_bytes_alloc(&read_file(path)?)),
+                None => None,
+            },
+        })
+    }
+}
+
+// A game's log. With more than one session, it is kept until the game is
+// over, so that the logs of games played at the same time do not interleave.
+struct GameLog {
+    option_buf: Option<String>,
+}
+
+impl GameLog {
+    fn new(is_buffered: bool) -> Self {
+        Self {
+            option_buf: is_buffered.then(String::new),
+        }
+    }
+
+    fn is_immediate(&self) -> bool {
+        self.option_buf.is_none()
+    }
+
+    fn log(&mut self, level: Level, args: std::fmt::Arguments<'_>) {
+        match &mut self.option_buf {
+            None => log!(level, "{args}"),
+            Some(buf) => {
+                use std::fmt::Write;
+                let _ = writeln!(buf, "{level} {args}");
             }
+        }
+    }
+}
 
-            let events = check_ok(
-                client
-                    .post(format!("{url}/games/{gameid}/waitTurn"))
-                    .basic_auth(userid, None::<&str>)
-                    .send()?,
-            )?
-            .text()?;
+impl Drop for GameLog {
+    fn drop(&mut self) {
+        if let Some(buf) = &self.option_buf
+            && !buf.is_empty()
+        {
+            info!("game log\n{}", buf.trim_end());
+        }
+    }
+}
+
+macro_rules! log_info {
+    ($log:expr, $($arg:tt)+) => {
+        $log.log(Level::Info, format_args!($($arg)+))
+    };
+}
+
+macro_rules! log_warn {
+    ($log:expr, $($arg:tt)+) => {
+        $log.log(Level::Warn, format_args!($($arg)+))
+    };
+}
+
+// what every game of a run shares.
+struct Context<'a, N: kwg::Node> {
+    url: &'a str,
+    gametag: &'a str,
+    userid: &'a str,
+    strategy: strategy::Strategy,
+    seeder: &'a seed::Seeder,
+    option_gcg_dir: Option<&'a str>,
+    loaded: &'a Loaded<N>,
+    rack_reader: alphabet::AlphabetReader,
+    play_reader: std::sync::Arc<alphabet::AlphabetReader>,
+}
 
-            if false {
-                show_all(&client, url, &gameid)?; // our turn
+fn play_game<N: kwg::Node>(
+    Context {
+        url,
+        gametag,
+        userid,
+        strategy,
+        seeder,
+        option_gcg_dir,
+        loaded:
+            Loaded {
+                game_config,
+                kwg,
+                klv,
+                noleave_klv,
+                option_common_word_kwg,
+            },
+        rack_reader,
+        play_reader,
+    }: &Context<'_, N>,
+    client: &reqwest::blocking::Client,
+    log: &mut GameLog,
+) -> error::Returns<(results::GameRecord, bool)> {
+    let dim = game_config.board_layout().dim();
+    let alphabet = game_config.alphabet();
+    let mut game_state = game_state::GameState::new(game_config);
+    let mut move_generator = movegen::KurniaMoveGenerator::new(game_config);
+    let used_kwg = if strategy.use_common_word {
+        option_common_word_kwg
+            .as_ref()
+            .ok_or("no common-word kwg")?
+    } else {
+        kwg
+    };
+    let used_klv = match strategy.picker {
+        strategy::Picker::NoLeave => noleave_klv,
+        _ => klv,
+    };
+    // with the settings for the rollouts in sim.rs, if used instead.
+    let (mut move_filter, mut move_picker, option_sim_params) = match strategy.picker {
+        strategy::Picker::Hasty | strategy::Picker::NoLeave => (
+            move_filter::GenMoves::Unfiltered,
+            move_picker::MovePicker::Hasty,
+            None,
+        ),
+        strategy::Picker::Tilt(bot_level) => (
+            move_filter::GenMoves::Tilt {
+                // against the whole lexicon, as for the bot.
+                tilt: move_filter::Tilt::new(
+                    game_config,
+                    kwg,
+                    move_filter::Tilt::length_importances(),
+                ),
+                bot_level,
+            },
+            move_picker::MovePicker::Hasty,
+            None,
+        ),
+        strategy::Picker::Sim(None) => (
+            move_filter::GenMoves::Unfiltered,
+            move_picker::MovePicker::Simmer(move_picker::Simmer::new(
+                game_config,
+                used_kwg,
+                used_klv,
+            )),
+            None,
+        ),
+        strategy::Picker::Sim(Some(sim_params)) => (
+            move_filter::GenMoves::Unfiltered,
+            move_picker::MovePicker::Hasty,
+            Some(sim_params),
+        ),
+    };
+    let mut move_to_send_buf = String::new();
+
+    let mut tile_placer = position::TilePlacer::new(
+        std::sync::Arc::clone(game_config),
+        std::sync::Arc::clone(play_reader),
+    );
+
+    // a game may have been made even if the answer did not arrive, so this is
+    // only sent again if it could not be sent.
+    let mut backoff = Backoff::new();
+    let gameid = loop {
+        let result = client
+            .post(format!("{url}/games"))
+            .basic_auth(userid, None::<&str>)
+            .body(gametag.to_string())
+            .timeout(REQUEST_TIMEOUT)
+            .send();
+        match result {
+            Err(ref err) if err.is_connect() => {
+                backoff.wait(log, "", "new game", err.to_string())?
             }
+            _ => match text_of(result)? {
+                Ok(gameid) => break gameid,
+                Err(reason) => {
+                    return Err(Box::new(Abandoned {
+                        gameid: String::new(),
+                        reason: format!("new game: {reason}"),
+                    }));
+                }
+            },
+        }
+    };
+    if gameid.is_empty() {
+        return Err("no gameid".into());
+    }
+    log_info!(log, "gameid={gameid}");
+    // assume no need to encode the gameid
 
-            info!("waitTurn\n{events}");
-
-            let mut event_lines = events.lines().collect::<Vec<_>>();
-            let last_line = event_lines.pop().ok_or("no lines")?;
-            let (is_ongoing, my_score, your_score) = {
-                let mut tok = last_line.split(':');
-                match tok.next() {
-                    Some("o") => {
-                        let score0 = i32::from_str(tok.next().ok_or("ongoing my score")?)?;
-                        let score1 = i32::from_str(tok.next().ok_or("ongoing your score")?)?;
-                        let my_rack = tok.next().ok_or("my rack")?;
-                        if tok.next().is_some() {
-                            return Err("too many tokens".into());
-                        }
-                        parse_rack(&rack_reader, my_rack, &mut rack)?;
-                        (true, score0, score1)
-                    }
-                    Some("f") => {
-                        let score0 = i32::from_str(tok.next().ok_or("final my score")?)?;
-                        let score1 = i32::from_str(tok.next().ok_or("final your score")?)?;
-                        if tok.next().is_some() {
-                            return Err("too many tokens".into());
-                        }
-                        rack.clear();
-                        (false, score0, score1)
+    let mut rack = Vec::new();
+    let mut option_went_first = None;
+    let mut make_play_backoff = Backoff::new();
+    // the racks of our plays, for checking the gcg, by the index of the event
+    // line each play would get. a play sent again replaces the one before, and
+    // only the plays the events have are checked.
+    let mut racks_by_line = std::collections::HashMap::new();
+    let mut option_gcg = None;
+    let (record, has_gcg_mismatch) = loop {
+        if false {
+            show_all(&client, url, &gameid)?; // anyone's turn
+        }
+
+        let mut wait_turn_backoff = Backoff::new();
+        let mut num_wait_turn_timeouts = 0;
+        let events = loop {
+            let result = client
+                .post(format!("{url}/games/{gameid}/waitTurn"))
+                .basic_auth(userid, None::<&str>)
+                .timeout(WAIT_TURN_TIMEOUT)
+                .send();
+            match result {
+                Err(ref err) if err.is_timeout() => {
+                    num_wait_turn_timeouts += 1;
+                    if num_wait_turn_timeouts > MAX_WAIT_TURN_TIMEOUTS {
+                        return Err(Box::new(Abandoned {
+                            gameid: gameid.clone(),
+                            reason: format!("waitTurn timed out {num_wait_turn_timeouts} times"),
+                        }));
                     }
-                    _ => return Err(format!("bad last line {last_line:?}").into()),
+                    log_info!(log, "still waiting for turn");
                 }
-            };
-
-            // note: this game only counts if neither player gets -1.
-            // when someone times out their score becomes -1 and the game is already deleted.
-            let this_game_counts = my_score >= 0 && your_score >= 0;
-
-            if this_game_counts {
-                // this would 404 in the -1 case.
-                let gcg = check_ok(
-                    client
-                        .post(format!("{url}/games/{gameid}/waitTurn"))
-                        .basic_auth(userid, None::<&str>)
-                        .header(reqwest::header::ACCEPT, "application/gcg")
-                        .send()?,
-                )?
-                .text()?;
-                info!("waitTurn.gcg\n{gcg}");
+                _ => match abandon_on_error(&gameid, "waitTurn", text_of(result))? {
+                    Ok(events) => break events,
+                    Err(reason) => wait_turn_backoff.wait(log, &gameid, "waitTurn", reason)?,
+                },
             }
+        };
 
-            let mut should_challenge = false;
-            {
-                // rebuild the state
-                game_state.reset();
-                game_state.players[0].score = my_score;
-                game_state.players[1].score = your_score;
-
-                let mut last_tile_placement = !0;
-                for (i, event) in event_lines.iter().enumerate() {
-                    if event == &"c" {
-                        // last placement was challenged, do not place it
-                        last_tile_placement = !0;
-                    } else if event != &"p" {
-                        // "p" means pass/exchange
-                        if last_tile_placement != !0 {
-                            place_tiles(
-                                &mut game_state.board_tiles,
-                                event_lines[last_tile_placement],
-                                None,
-                            )?;
-                        }
-                        last_tile_placement = i;
+        if false {
+            show_all(&client, url, &gameid)?; // our turn
+        }
+
+        log_info!(log, "waitTurn\n{events}");
+
+        let mut event_lines = events.lines().collect::<Vec<_>>();
+        let last_line = event_lines.pop().ok_or("no lines")?;
+        let (is_ongoing, my_score, your_score) = {
+            let mut tok = last_line.split(':');
+            match tok.next() {
+                Some("o") => {
+                    let score0 = i32::from_str(tok.next().ok_or("ongoing my score")?)?;
+                    let score1 = i32::from_str(tok.next().ok_or("ongoing your score")?)?;
+                    let my_rack = tok.next().ok_or("my rack")?;
+                    if tok.next().is_some() {
+                        return Err("too many tokens".into());
                     }
+                    parse_rack(rack_reader, my_rack, &mut rack)?;
+                    (true, score0, score1)
                 }
-                if last_tile_placement != !0 {
-                    let is_valid = place_tiles(
-                        &mut game_state.board_tiles,
-                        event_lines[last_tile_placement],
-                        if last_tile_placement == event_lines.len() - 1 {
-                            Some(&kwg)
-                        } else {
-                            None
-                        },
-                    )?;
-                    if !is_valid {
-                        should_challenge = true;
+                Some("f") => {
+                    let score0 = i32::from_str(tok.next().ok_or("final my score")?)?;
+                    let score1 = i32::from_str(tok.next().ok_or("final your score")?)?;
+                    if tok.next().is_some() {
+                        return Err("too many tokens".into());
                     }
+                    rack.clear();
+                    (false, score0, score1)
                 }
+                _ => return Err(format!("bad last line {last_line:?}").into()),
             }
+        };
 
-            available_tally_buf.clear();
-            available_tally_buf.reserve(alphabet.len() as usize);
-            available_tally_buf.extend((0..alphabet.len()).map(|tile| alphabet.freq(tile)));
-            for &tile in &rack {
-                if tile > alphabet_len_without_blank {
-                    wolges::return_error!(format!(
-                        "rack has invalid tile {tile}, alphabet size is {alphabet_len_without_blank}"
-                    ));
-                }
-                if available_tally_buf[tile as usize] > 0 {
-                    available_tally_buf[tile as usize] -= 1;
-                } else {
-                    wolges::return_error!(format!(
-                        "too many tile {} (bag contains only {})",
-                        tile,
-                        alphabet.freq(tile),
-                    ));
+        if is_ongoing && option_went_first.is_none() {
+            option_went_first = Some(event_lines.is_empty());
+        }
+
+        // note: this game only counts if neither player gets -1.
+        // when someone times out their score becomes -1 and the game is already deleted.
+        let this_game_counts = my_score >= 0 && your_score >= 0;
+
+        if this_game_counts {
+            // this would 404 in the -1 case.
+            let gcg = send_idempotent(log, &gameid, "waitTurn.gcg", || {
+                client
+                    .post(format!("{url}/games/{gameid}/waitTurn"))
+                    .basic_auth(userid, None::<&str>)
+                    .header(reqwest::header::ACCEPT, "application/gcg")
+            })?;
+            log_info!(log, "waitTurn.gcg\n{gcg}");
+            option_gcg = Some(gcg);
+        }
+
+        let mut should_challenge = false;
+        {
+            // rebuild the state
+            game_state.reset();
+            game_state.players[0].score = my_score;
+            game_state.players[1].score = your_score;
+
+            let mut last_tile_placement = !0;
+            for (i, event) in event_lines.iter().enumerate() {
+                if event == &"c" {
+                    // last placement was challenged, do not place it
+                    last_tile_placement = !0;
+                } else if event != &"p" {
+                    // "p" means pass/exchange
+                    if last_tile_placement != !0 {
+                        tile_placer.place(
+                            &mut game_state.board_tiles,
+                            &position::Placement::of_bvb(event_lines[last_tile_placement], dim)?,
+                        )?;
+                    }
+                    last_tile_placement = i;
                 }
             }
-            for &board_tile in game_state.board_tiles.iter() {
-                if board_tile != 0 {
-                    let tile = board_tile & !((board_tile as i8) >> 7) as u8;
-                    if available_tally_buf[tile as usize] > 0 {
-                        available_tally_buf[tile as usize] -= 1;
-                    } else {
-                        wolges::return_error!(format!(
-                            "too many tile {} (bag contains only {})",
-                            tile,
-                            alphabet.freq(tile),
-                        ));
-                    }
+            if last_tile_placement != !0 {
+                let placement = position::Placement::of_bvb(event_lines[last_tile_placement], dim)?;
+                tile_placer.place(&mut game_state.board_tiles, &placement)?;
+                if last_tile_placement == event_lines.len() - 1
+                    && !tile_placer.forms_valid_words(&game_state.board_tiles, &placement, kwg)?
+                {
+                    should_challenge = true;
                 }
             }
+        }
 
-            // fill the bag in sorted order for viewing
-            game_state.bag.set_from_iter(
-                (0u8..)
-                    .zip(available_tally_buf.iter())
-                    .flat_map(|(tile, &count)| std::iter::repeat_n(tile, count as usize)),
-            );
+        // just fill in one rack, leave opponent's rack empty
+        game_state.players[0].rack.clone_from(&rack);
+        // fill the bag in sorted order for viewing
+        position::fill_bag(game_config, &mut game_state)?;
+        // the board goes to stdout, which the sessions share.
+        if log.is_immediate() {
+            display::print_game_state(game_config, &game_state, None);
+        }
 
-            // just fill in one rack, leave opponent's rack empty
-            game_state.players[0].rack.clone_from(&rack);
-            display::print_game_state(&game_config, &game_state, None);
-
-            if !is_ongoing {
-                let score0 = game_state.players[0].score; // always our score
-                let score1 = game_state.players[1].score; // always opponent's score
-                let (this_win, this_loss) = match score0.cmp(&score1) {
-                    std::cmp::Ordering::Greater => (1.0, 0.0),
-                    std::cmp::Ordering::Less => (0.0, 1.0),
-                    std::cmp::Ordering::Equal => (0.5, 0.5),
+        if !is_ongoing {
+            let went_first = option_went_first.unwrap_or(false);
+            let mut has_gcg_mismatch = false;
+            if let Some(gcg) = option_gcg.as_ref().filter(|_| this_game_counts) {
+                let my_move_racks = results::is_by_first_player(&event_lines)
+                    .into_iter()
+                    .zip(event_lines.iter())
+                    .enumerate()
+                    .filter(|&(_, (is_by_first, &event))| is_by_first == went_first && event != "c")
+                    .filter_map(|(i, _)| racks_by_line.remove(&i))
+                    .collect::<Vec<_>>();
+                if let Some(gcg_dir) = option_gcg_dir {
+                    let path = format!("{gcg_dir}/{gameid}-{userid}.gcg");
+                    std::fs::write(&path, gcg).map_err(|err| format!("{path}: {err}"))?;
+                    log_info!(log, "wrote {path}");
+                }
+                let mismatches = match gcg::GcgGame::parse(gcg) {
+                    Ok(gcg_game) => gcg_game.mismatches(
+                        if went_first { 0 } else { 1 },
+                        &game_state.board_tiles,
+                        (my_score, your_score),
+                        &my_move_racks,
+                        &mut tile_placer,
+                        rack_reader,
+                        dim,
+                    )?,
+                    Err(err) => vec![format!("cannot read it: {err}")],
                 };
-                let this_spread = score0 - score1;
-                if this_game_counts {
-                    total_win += this_win;
-                    total_loss += this_loss;
-                    total_spread += this_spread as i64;
-                    num_completed += 1;
-                    info!(
-                        "Result: Game {num_completed}: {score0}-{score1} ({this_win}-{this_loss} {this_spread}), Total: {total_win}-{total_loss} {total_spread}"
-                    );
-                } else {
-                    info!(
-                        "Result: Invalid Game: {score0}-{score1} ({this_win}-{this_loss} {this_spread}), Not Updated Total (from {num_completed} games): {total_win}-{total_loss} {total_spread}"
-                    );
+                for mismatch in mismatches.iter() {
+                    log_warn!(log, "gcg mismatch, a server or client bug: {mismatch}");
                 }
-                break;
+                has_gcg_mismatch = !mismatches.is_empty();
             }
+            break (
+                results::GameRecord::of_events(
+                    &gameid,
+                    this_game_counts,
+                    went_first,
+                    (my_score, your_score),
+                    &event_lines,
+                    play_reader,
+                    game_config.rack_size() as usize,
+                )?,
+                has_gcg_mismatch,
+            );
+        }
 
-            move_to_send_buf.clear();
-            if should_challenge {
-                move_to_send_buf.push('c');
-                info!("Challenging last move");
-            } else {
-                let board_snapshot = &movegen::BoardSnapshot {
-                    board_tiles: &game_state.board_tiles,
-                    game_config: &game_config,
-                    kwg: &kwg,
-                    klv: &klv,
-                };
-                move_picker.pick_a_move(
+        move_to_send_buf.clear();
+        if should_challenge {
+            move_to_send_buf.push('c');
+            log_info!(log, "Challenging last move");
+        } else {
+            let seed = seeder.request_seed(&gameid, event_lines.len());
+            let mut rng = rand::rngs::ChaCha20Rng::seed_from_u64(seed);
+            if let move_filter::GenMoves::Tilt {
+                ref mut tilt,
+                bot_level,
+            } = move_filter
+            {
+                tilt.tilt_by_rng(&mut rng, bot_level);
+            }
+            let board_snapshot = &movegen::BoardSnapshot {
+                board_tiles: &game_state.board_tiles,
+                game_config,
+                kwg: used_kwg,
+                klv: used_klv,
+            };
+            match &option_sim_params {
+                // the other rack is not known, its tiles are in the bag.
+                Some(sim_params) => {
+                    sim::pick_a_move(
+                        sim_params,
+                        &game_state,
+                        game_config,
+                        used_kwg,
+                        used_klv,
+                        &mut move_generator,
+                        None,
+                        &mut rng,
+                    )?;
+                }
+                None => move_picker.pick_a_move(
                     &mut move_filter,
                     &mut move_generator,
                     board_snapshot,
                     &game_state,
                     &game_state.current_player().rack,
-                );
-                let plays = &mut move_generator.plays;
-                let play = &plays[0].play; // assume at least there's always Pass
-
-                match &play {
-                    movegen::Play::Exchange { tiles } => {
-                        if tiles.is_empty() {
-                            move_to_send_buf.push('p');
-                        } else {
-                            move_to_send_buf.push_str("p:");
-                            for &tile in tiles.iter() {
-                                //move_to_send_buf.push_str(from_lowercase_rack(alphabet, tile).unwrap());
-                                move_to_send_buf.push_str(alphabet.of_rack(tile).unwrap());
-                            }
-                        }
-                    }
-                    movegen::Play::Place {
-                        down,
-                        lane,
-                        idx,
-                        word,
-                        score: _,
-                    } => {
-                        if *down {
-                            write!(move_to_send_buf, "{}{}:", display::column(*lane), idx + 1)?;
-                        } else {
-                            write!(move_to_send_buf, "{}{}:", lane + 1, display::column(*idx))?;
-                        }
-                        for &tile in word.iter() {
-                            if tile == 0 {
-                                move_to_send_buf.push('.');
-                            } else {
-                                move_to_send_buf.push_str(alphabet.of_board(tile).unwrap());
-                            }
-                        }
-                    }
-                };
+                ),
+            }
+            let plays = &mut move_generator.plays;
+            let play = &plays[0].play; // assume at least there's always Pass
+
+            encode::play_to_bvb(play, alphabet, &mut move_to_send_buf)?;
+            racks_by_line.insert(event_lines.len(), rack.clone());
 
-                info!("Playing: {}", play.fmt(board_snapshot));
-                RNG.with(|rng| {
-                    let mut rng = rng.borrow_mut();
-                    game_state.bag.shuffle(&mut *rng);
-                    game_state.play(&game_config, &mut *rng, play)
-                })?;
-                game_state.next_turn(); // for display
-                // bag is displayed unsorted
-                display::print_game_state(&game_config, &game_state, None);
+            log_info!(log, "Playing: {} (seed {seed})", play.fmt(board_snapshot));
+            game_state.bag.shuffle(&mut rng);
+            game_state.play(game_config, &mut rng, play)?;
+            game_state.next_turn(); // for display
+            // bag is displayed unsorted
+            if log.is_immediate() {
+                display::print_game_state(game_config, &game_state, None);
             }
-            info!("makePlay {move_to_send_buf}");
+        }
+        log_info!(log, "makePlay {move_to_send_buf}");
 
-            check_ok(
+        // the play may or may not have been made when this fails. waitTurn
+        // then tells which, as the position is rebuilt from the events, and
+        // the same play is sent again if it is still our turn.
+        match abandon_on_error(
+            &gameid,
+            "makePlay",
+            text_of(
                 client
                     .post(format!("{url}/games/{gameid}/makePlay"))
                     .basic_auth(userid, None::<&str>)
                     .body(move_to_send_buf.clone())
-                    .send()?,
-            )?
-            .text()?;
+                    .timeout(REQUEST_TIMEOUT)
+                    .send(),
+            ),
+        )? {
+            Ok(_) => make_play_backoff = Backoff::new(),
+            Err(reason) => make_play_backoff.wait(log, &gameid, "makePlay", reason)?,
         }
+    };
+
+    // delete, this is allowed to fail
+    match send_idempotent(log, &gameid, "delete", || {
+        client
+            .delete(format!("{url}/games/{gameid}"))
+            .basic_auth(userid, None::<&str>)
+    }) {
+        Ok(_) => log_info!(log, "game {gameid} deleted"),
+        Err(err) => log_warn!(log, "game {gameid} not deleted: {err}"),
+    }
+    Ok((record, has_gcg_mismatch))
+}
+
+// The games of a run so far, from all sessions.
+struct Tally {
+    // which seat, when playing both.
+    label: String,
+    total_win: f64,
+    total_loss: f64,
+    total_spread: i64,
+    num_completed: usize,
+    num_in_progress: usize,
+    // set when a session fails, so the others stop starting games.
+    is_aborted: bool,
+    records: Vec<results::GameRecord>,
+    option_results_file: Option<results::ResultsFile>,
+    num_gcg_mismatches: usize,
+}
 
-        // delete, this is allowed to fail
+impl Tally {
+    // starts with the records of a resumed run.
+    fn new(
+        label: String,
+        records: Vec<results::GameRecord>,
+        option_results_file: Option<results::ResultsFile>,
+    ) -> Self {
+        let mut tally = Self {
+            label,
+            total_win: 0.0,
+            total_loss: 0.0,
+            total_spread: 0,
+            num_completed: 0,
+            num_in_progress: 0,
+            is_aborted: false,
+            records: Vec::new(),
+            option_results_file,
+            num_gcg_mismatches: 0,
+        };
+        for record in records.iter().filter(|record| record.counts) {
+            let this_win = stats::win_share(record.my_score, record.your_score);
+            tally.total_win += this_win;
+            tally.total_loss += 1.0 - this_win;
+            tally.total_spread += record.spread as i64;
+            tally.num_completed += 1;
+        }
+        if !records.is_empty() {
+            info!(
+                "{}Resuming after {} games: Total: {}-{} {}",
+                tally.label,
+                tally.num_completed,
+                tally.total_win,
+                tally.total_loss,
+                tally.total_spread
+            );
+        }
+        tally.records = records;
+        tally
+    }
+
+    fn add(&mut self, record: results::GameRecord) -> error::Returns<()> {
+        if let Some(results_file) = &mut self.option_results_file {
+            results_file.append(&record)?;
+        }
+        let score0 = record.my_score;
+        let score1 = record.your_score;
+        let this_win = stats::win_share(score0, score1);
+        let this_loss = 1.0 - this_win;
+        let this_spread = record.spread;
+        if record.counts {
+            self.total_win += this_win;
+            self.total_loss += this_loss;
+            self.total_spread += this_spread as i64;
+            self.num_completed += 1;
+        }
+        let Self {
+            label,
+            total_win,
+            total_loss,
+            total_spread,
+            num_completed,
+            ..
+        } = self;
+        if record.counts {
+            info!(
+                "{label}Result: Game {num_completed}: {score0}-{score1} ({this_win}-{this_loss} {this_spread}), Total: {total_win}-{total_loss} {total_spread}"
+            );
+        } else {
+            info!(
+                "{label}Result: Invalid Game: {score0}-{score1} ({this_win}-{this_loss} {this_spread}), Not Updated Total (from {num_completed} games): {total_win}-{total_loss} {total_spread}"
+            );
+        }
+        self.records.push(record);
+        Ok(())
+    }
+}
+
+// Plays games on its own connection until the run has enough of them.
+fn run_session<N: kwg::Node>(
+    context: &Context<'_, N>,
+    tally: &std::sync::Mutex<Tally>,
+    num_games: usize,
+    is_buffered: bool,
+) -> error::Returns<()> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(None)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()?;
+    loop {
         {
-            let resp = client
-                .delete(format!("{url}/games/{gameid}"))
-                .basic_auth(userid, None::<&str>)
-                .send()?;
-            let status = resp.status();
-            if status.is_success() {
-                resp.text()?;
-                info!("game {gameid} deleted");
-            } else {
-                warn!(
-                    "game {} not deleted: {:?}: {}",
-                    gameid,
-                    status,
-                    resp.text()?
-                );
+            let mut tally = tally.lock().unwrap();
+            if tally.is_aborted || tally.num_completed + tally.num_in_progress >= num_games {
+                return Ok(());
+            }
+            tally.num_in_progress += 1;
+        }
+        let mut log = GameLog::new(is_buffered);
+        let result = play_game(context, &client, &mut log);
+        drop(log);
+        let mut tally = tally.lock().unwrap();
+        tally.num_in_progress -= 1;
+        match result {
+            Ok((record, has_gcg_mismatch)) => {
+                tally.num_gcg_mismatches += has_gcg_mismatch as usize;
+                tally.add(record)?;
+            }
+            Err(err) if err.is::<Abandoned>() => {
+                warn!("{}{err}", tally.label);
+                let gameid = err.downcast_ref::<Abandoned>().unwrap().gameid.clone();
+                tally.add(results::GameRecord::abandoned(&gameid))?;
+            }
+            Err(err) => {
+                tally.is_aborted = true;
+                return Err(err);
+            }
+        }
+    }
+}
+
+fn do_it<N: kwg::Node + Sync>(
+    url: &str,
+    gametag: &str,
+    userid: &str,
+    num_games: usize,
+    seeder: &seed::Seeder,
+    options: &Options,
+    loaded: &Loaded<N>,
+) -> error::Returns<()> {
+    let alphabet = loaded.game_config.alphabet();
+    let (option_results_file, records) = match &options.option_results {
+        Some((path, resume)) => {
+            let (results_file, records) = results::ResultsFile::open(path, *resume)?;
+            (Some(results_file), records)
+        }
+        None => (None, Vec::new()),
+    };
+    // when playing both seats, the results are for userid 0, and userid 1
+    // resumes from the same games seen from the other side.
+    let seats = if userid == "both" {
+        let other_records = records.iter().map(|record| record.mirrored()).collect();
+        vec![
+            (
+                "0",
+                options.strategy,
+                Tally::new("[0] ".into(), records, option_results_file),
+            ),
+            (
+                "1",
+                options.other_strategy,
+                Tally::new("[1] ".into(), other_records, None),
+            ),
+        ]
+    } else {
+        vec![(
+            userid,
+            options.strategy,
+            Tally::new(String::new(), records, option_results_file),
+        )]
+    }
+    .into_iter()
+    .map(|(userid, strategy, tally)| {
+        (
+            Context {
+                url,
+                gametag,
+                userid,
+                strategy,
+                seeder,
+                option_gcg_dir: options.option_gcg_dir.as_deref(),
+                loaded,
+                //rack_reader: new_for_lowercase_racks(alphabet),
+                rack_reader: alphabet::AlphabetReader::new_for_racks(alphabet),
+                play_reader: std::sync::Arc::new(alphabet::AlphabetReader::new_for_plays(alphabet)),
+            },
+            std::sync::Mutex::new(tally),
+        )
+    })
+    .collect::<Vec<_>>();
+
+    if let Some(gcg_dir) = &options.option_gcg_dir {
+        std::fs::create_dir_all(gcg_dir).map_err(|err| format!("{gcg_dir}: {err}"))?;
+    }
+    let num_sessions = options.num_sessions;
+    let is_buffered = seats.len() * num_sessions > 1;
+    std::thread::scope(|s| -> error::Returns<()> {
+        let handles = seats
+            .iter()
+            .flat_map(|(context, tally)| {
+                (0..num_sessions).map(move |_| {
+                    s.spawn(move || {
+                        run_session(context, tally, num_games, is_buffered)
+                            .map_err(|err| err.to_string())
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut option_err = None;
+        for handle in handles {
+            if let Err(err) = handle.join().map_err(|_| "session panicked")? {
+                option_err.get_or_insert(err);
+            }
+        }
+        match option_err {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    })?;
+
+    for (_, tally) in seats {
+        let Tally {
+            label,
+            total_win,
+            total_loss,
+            total_spread,
+            records,
+            option_results_file,
+            num_gcg_mismatches,
+            ..
+        } = tally.into_inner().unwrap();
+        info!(
+            "{label}Final Result: After Game {num_games}: Total: {total_win}-{total_loss} {total_spread}"
+        );
+        if num_gcg_mismatches != 0 {
+            warn!("{label}{num_gcg_mismatches} games did not match their gcg");
+        }
+        if let Some(summary) = results::Summary::of(&records) {
+            info!("{label}Summary: {summary}");
+            if let Some(results_file) = &option_results_file {
+                results_file.write_summary(&summary)?;
             }
         }
     }
-    info!("Final Result: After Game {num_games}: Total: {total_win}-{total_loss} {total_spread}");
     Ok(())
 }
 
@@ -585,16 +1035,53 @@ fn main() -> error::Returns<()> {
     env_logger::init();
 
     let args = std::env::args().collect::<Vec<_>>();
-    if args.len() <= 4 || (args[3] != "0" && args[3] != "1") {
+    if args.len() <= 4 || (args[3] != "0" && args[3] != "1" && args[3] != "both") {
         println!(
-            "args: http://127.0.0.1:8001 gametag userid num
+            "args: http://127.0.0.1:8001 gametag userid num [options]
   userid
-    0 or 1
+    0 or 1, or both to play both seats from here
   num
-    number of games"
+    number of games
+  options
+    --lexicon CSW21
+    --leave english.klv
+    --language english (or catalan, french, german, norwegian, polish, spanish)
+    --variant classic (or super, for catalan and english)
+    --kwg-node 22 (or 24, which reads LEXICON.kbwg)
+    --results results.csv (or results.json, one record per line)
+    --resume results.csv (appends to it, counting the games already there)
+    --sessions 1 (games played at the same time)
+    --strategy hasty (or noleave, sim, tilt1 to tilt5, any of them +common)
+      sim,candidates=20,plies=3,iterations=500 sims with the rollouts in sim.rs
+    --other-strategy hasty (for userid 1 when playing both seats)
+    --common-word CEL.kwg (the words +common plays)
+    --gcg-dir gcgs (saves the gcg of each finished game there)"
         );
         Ok(())
     } else {
-        do_it::<kwg::Node22>(&args[1], &args[2], &args[3], usize::from_str(&args[4])?)
+        let options = Options::parse(&args[5..])?;
+        let seeder = seed::Seeder::from_env()?;
+        info!("master seed: {}", seeder.master_seed());
+        let num_games = usize::from_str(&args[4])?;
+        match options.kwg_node {
+            24 => do_it::<kwg::Node24>(
+                &args[1],
+                &args[2],
+                &args[3],
+                num_games,
+                &seeder,
+                &options,
+                &Loaded::load(&options)?,
+            ),
+            _ => do_it::<kwg::Node22>(
+                &args[1],
+                &args[2],
+                &args[3],
+                num_games,
+                &seeder,
+                &options,
+                &Loaded::load(&options)?,
+            ),
+        }
     }
 }
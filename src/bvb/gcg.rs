@@ -0,0 +1,141 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Checks the GCG of a finished game, as the server wrote it, against the game
+// as bvb rebuilt it from the plain event lines. A difference means a bug in
+// one of them.
+
+use omgbot::{parse_rack, position};
+use wolges::*;
+
+pub struct GcgGame<'a> {
+    // in the order of their first turn.
+    nicknames: Vec<&'a str>,
+    // the cumulative score on the last line of each player.
+    final_scores: Vec<i32>,
+    // coord and played tiles of the placements that were not withdrawn.
+    placements: Vec<(&'a str, &'a str)>,
+    // for each player, the rack before each placement, pass or exchange.
+    move_racks: Vec<Vec<&'a str>>,
+}
+
+impl<'a> GcgGame<'a> {
+    pub fn parse(gcg: &'a str) -> error::Returns<Self> {
+        let mut game = Self {
+            nicknames: Vec::new(),
+            final_scores: Vec::new(),
+            placements: Vec::new(),
+            move_racks: Vec::new(),
+        };
+        for line in gcg.lines() {
+            let Some(line) = line.strip_prefix('>') else {
+                continue;
+            };
+            let (nickname, rest) = line
+                .split_once(':')
+                .ok_or_else(|| format!("bad gcg line {line:?}"))?;
+            let tokens = rest.split_whitespace().collect::<Vec<_>>();
+            if tokens.len() < 3 {
+                wolges::return_error!(format!("bad gcg line {line:?}"));
+            }
+            let player = match game.nicknames.iter().position(|&n| n == nickname) {
+                Some(player) => player,
+                None => {
+                    game.nicknames.push(nickname);
+                    game.final_scores.push(0);
+                    game.move_racks.push(Vec::new());
+                    game.nicknames.len() - 1
+                }
+            };
+            game.final_scores[player] = tokens[tokens.len() - 1]
+                .parse()
+                .map_err(|err| format!("bad cumulative score in {line:?}: {err}"))?;
+            let rack = tokens[0];
+            let action = tokens[1];
+            if rack.starts_with('(') || action.starts_with('(') {
+                // end of game, time penalty or challenge bonus.
+            } else if action == "--" {
+                game.placements
+                    .pop()
+                    .ok_or_else(|| format!("nothing to withdraw in {line:?}"))?;
+            } else if action.starts_with('-') {
+                game.move_racks[player].push(rack);
+            } else {
+                if tokens.len() < 4 {
+                    wolges::return_error!(format!("bad gcg line {line:?}"));
+                }
+                game.placements.push((action, tokens[2]));
+                game.move_racks[player].push(rack);
+            }
+        }
+        Ok(game)
+    }
+
+    // Returns what differs, with my_player being 0 if bvb went first. The board
+    // is rebuilt on a copy of board_tiles.
+    #[expect(clippy::too_many_arguments)]
+    pub fn mismatches(
+        &self,
+        my_player: usize,
+        board_tiles: &[u8],
+        (my_score, your_score): (i32, i32),
+        my_move_racks: &[Vec<u8>],
+        tile_placer: &mut position::TilePlacer,
+        rack_reader: &alphabet::AlphabetReader,
+        dim: &matrix::Dim,
+    ) -> error::Returns<Vec<String>> {
+        let mut mismatches = Vec::new();
+        if self.nicknames.len() != 2 {
+            mismatches.push(format!("{} players in the gcg", self.nicknames.len()));
+            return Ok(mismatches);
+        }
+        let your_player = 1 - my_player;
+
+        let mut gcg_board_tiles = vec![0u8; board_tiles.len()];
+        for &(coord, played_tiles) in self.placements.iter() {
+            let placed = position::Placement::of_coord(coord, played_tiles, dim)
+                .and_then(|placement| tile_placer.place(&mut gcg_board_tiles, &placement));
+            if let Err(err) = placed {
+                mismatches.push(format!("cannot place {coord} {played_tiles}: {err}"));
+            }
+        }
+        let num_differing_squares = board_tiles
+            .iter()
+            .zip(gcg_board_tiles.iter())
+            .filter(|(a, b)| a != b)
+            .count();
+        if num_differing_squares != 0 {
+            mismatches.push(format!(
+                "{num_differing_squares} squares differ on the board"
+            ));
+        }
+
+        if self.final_scores[my_player] != my_score || self.final_scores[your_player] != your_score
+        {
+            mismatches.push(format!(
+                "gcg scores are {}-{}, not {my_score}-{your_score}",
+                self.final_scores[my_player], self.final_scores[your_player]
+            ));
+        }
+
+        let gcg_racks = &self.move_racks[my_player];
+        if gcg_racks.len() != my_move_racks.len() {
+            mismatches.push(format!(
+                "gcg has {} moves, not {}",
+                gcg_racks.len(),
+                my_move_racks.len()
+            ));
+        }
+        let mut gcg_rack = Vec::new();
+        let mut my_rack = Vec::new();
+        for (i, (gcg_rack_str, rack)) in gcg_racks.iter().zip(my_move_racks.iter()).enumerate() {
+            parse_rack(rack_reader, gcg_rack_str, &mut gcg_rack)?;
+            gcg_rack.sort_unstable();
+            my_rack.clone_from(rack);
+            my_rack.sort_unstable();
+            if gcg_rack != my_rack {
+                mismatches.push(format!("gcg rack for move {} is {gcg_rack_str}", i + 1));
+            }
+        }
+        Ok(mismatches)
+    }
+}
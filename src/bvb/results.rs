@@ -0,0 +1,402 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Per-game records of a run, and the summary of them. The records go to a CSV
+// file if its name ends in .csv, else to a file with one JSON record per line,
+// and the summary goes next to it as PATH.summary.json. Both formats can be
+// appended to, so a long run can be resumed.
+
+use omgbot::{parse_played_tiles, stats};
+use wolges::*;
+
+use std::io::Write;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct GameRecord {
+    pub gameid: String,
+    // false if someone timed out, such games are not in the summary.
+    pub counts: bool,
+    pub went_first: bool,
+    pub my_score: i32,
+    pub your_score: i32,
+    pub spread: i32,
+    pub my_bingos: u32,
+    pub your_bingos: u32,
+    pub my_exchanges: u32,
+    pub your_exchanges: u32,
+    pub my_passes: u32,
+    pub your_passes: u32,
+    pub my_challenges: u32,
+    pub your_challenges: u32,
+    // placements, exchanges and passes, not challenges.
+    pub num_moves: u32,
+}
+
+// Whether each line is by the player who went first. The players take turns,
+// except that after a successful challenge ("c"), the challenger moves again.
+pub fn is_by_first_player(event_lines: &[&str]) -> Vec<bool> {
+    let mut is_first_on_turn = true;
+    event_lines
+        .iter()
+        .map(|&event| {
+            let is_by_first = is_first_on_turn;
+            if event != "c" {
+                is_first_on_turn = !is_first_on_turn;
+            }
+            is_by_first
+        })
+        .collect()
+}
+
+const CSV_HEADER: &str = "gameid,counts,went_first,my_score,your_score,spread,my_bingos,your_bingos,my_exchanges,your_exchanges,my_passes,your_passes,my_challenges,your_challenges,num_moves";
+
+impl GameRecord {
+    // event_lines are as bvb gets them: "p" is a pass, "p:TILES" is an
+    // exchange, "c" takes back the placement before it, and everything else is
+    // a placement. See is_by_first_player for whose line is whose.
+    pub fn of_events(
+        gameid: &str,
+        counts: bool,
+        went_first: bool,
+        (my_score, your_score): (i32, i32),
+        event_lines: &[&str],
+        play_reader: &alphabet::AlphabetReader,
+        rack_size: usize,
+    ) -> error::Returns<Self> {
+        let mut record = Self {
+            gameid: gameid.to_string(),
+            counts,
+            went_first,
+            my_score,
+            your_score,
+            spread: my_score - your_score,
+            my_bingos: 0,
+            your_bingos: 0,
+            my_exchanges: 0,
+            your_exchanges: 0,
+            my_passes: 0,
+            your_passes: 0,
+            my_challenges: 0,
+            your_challenges: 0,
+            num_moves: event_lines.iter().filter(|&&event| event != "c").count() as u32,
+        };
+        let mut tiles = Vec::new();
+        for (i, (&event, is_by_first)) in event_lines
+            .iter()
+            .zip(is_by_first_player(event_lines))
+            .enumerate()
+        {
+            let is_mine = is_by_first == went_first;
+            let (bingos, exchanges, passes, challenges) = if is_mine {
+                (
+                    &mut record.my_bingos,
+                    &mut record.my_exchanges,
+                    &mut record.my_passes,
+                    &mut record.my_challenges,
+                )
+            } else {
+                (
+                    &mut record.your_bingos,
+                    &mut record.your_exchanges,
+                    &mut record.your_passes,
+                    &mut record.your_challenges,
+                )
+            };
+            if event == "p" {
+                *passes += 1;
+            } else if event.starts_with("p:") {
+                *exchanges += 1;
+            } else if event == "c" {
+                *challenges += 1;
+            } else if event_lines.get(i + 1) != Some(&"c") {
+                let (_, played_tiles) = event
+                    .split_once(':')
+                    .ok_or_else(|| format!("bad placement {event:?}"))?;
+                parse_played_tiles(play_reader, played_tiles, &mut tiles)?;
+                if tiles.iter().filter(|&&tile| tile != 0).count() >= rack_size {
+                    *bingos += 1;
+                }
+            }
+        }
+        Ok(record)
+    }
+
+    // a game given up on, which does not count.
+    pub fn abandoned(gameid: &str) -> Self {
+        Self {
+            gameid: gameid.to_string(),
+            counts: false,
+            went_first: false,
+            my_score: 0,
+            your_score: 0,
+            spread: 0,
+            my_bingos: 0,
+            your_bingos: 0,
+            my_exchanges: 0,
+            your_exchanges: 0,
+            my_passes: 0,
+            your_passes: 0,
+            my_challenges: 0,
+            your_challenges: 0,
+            num_moves: 0,
+        }
+    }
+
+    // the same game, seen by the other player.
+    pub fn mirrored(&self) -> Self {
+        Self {
+            gameid: self.gameid.clone(),
+            counts: self.counts,
+            went_first: !self.went_first,
+            my_score: self.your_score,
+            your_score: self.my_score,
+            spread: -self.spread,
+            my_bingos: self.your_bingos,
+            your_bingos: self.my_bingos,
+            my_exchanges: self.your_exchanges,
+            your_exchanges: self.my_exchanges,
+            my_passes: self.your_passes,
+            your_passes: self.my_passes,
+            my_challenges: self.your_challenges,
+            your_challenges: self.my_challenges,
+            num_moves: self.num_moves,
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.gameid,
+            self.counts,
+            self.went_first,
+            self.my_score,
+            self.your_score,
+            self.spread,
+            self.my_bingos,
+            self.your_bingos,
+            self.my_exchanges,
+            self.your_exchanges,
+            self.my_passes,
+            self.your_passes,
+            self.my_challenges,
+            self.your_challenges,
+            self.num_moves,
+        )
+    }
+
+    fn of_csv(line: &str) -> error::Returns<Self> {
+        let fields = line.split(',').collect::<Vec<_>>();
+        if fields.len() != CSV_HEADER.split(',').count() {
+            wolges::return_error!(format!("bad record {line:?}"));
+        }
+        Ok(Self {
+            gameid: fields[0].to_string(),
+            counts: fields[1].parse()?,
+            went_first: fields[2].parse()?,
+            my_score: fields[3].parse()?,
+            your_score: fields[4].parse()?,
+            spread: fields[5].parse()?,
+            my_bingos: fields[6].parse()?,
+            your_bingos: fields[7].parse()?,
+            my_exchanges: fields[8].parse()?,
+            your_exchanges: fields[9].parse()?,
+            my_passes: fields[10].parse()?,
+            your_passes: fields[11].parse()?,
+            my_challenges: fields[12].parse()?,
+            your_challenges: fields[13].parse()?,
+            num_moves: fields[14].parse()?,
+        })
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct Summary {
+    pub num_games: usize,
+    pub wins: f64,
+    pub losses: f64,
+    pub total_spread: i64,
+    pub mean_spread: f64,
+    pub mean_spread_stderr: f64,
+    pub win_rate: f64,
+    pub win_rate_low: f64,
+    pub win_rate_high: f64,
+    pub elo: f64,
+    pub elo_low: f64,
+    pub elo_high: f64,
+    pub mean_my_bingos: f64,
+    pub mean_your_bingos: f64,
+    pub mean_moves: f64,
+}
+
+impl Summary {
+    // None if no game counts.
+    pub fn of(records: &[GameRecord]) -> Option<Self> {
+        let records = records
+            .iter()
+            .filter(|record| record.counts)
+            .collect::<Vec<_>>();
+        if records.is_empty() {
+            return None;
+        }
+        let n = records.len() as f64;
+        let mean_of =
+            |f: fn(&GameRecord) -> f64| records.iter().map(|&record| f(record)).sum::<f64>() / n;
+        let wins = records
+            .iter()
+            .map(|record| stats::win_share(record.my_score, record.your_score))
+            .collect::<Vec<_>>();
+        let spreads = records
+            .iter()
+            .map(|record| record.spread as f64)
+            .collect::<Vec<_>>();
+        let (win_rate, win_rate_stderr) = stats::mean_and_stderr(&wins);
+        let (mean_spread, mean_spread_stderr) = stats::mean_and_stderr(&spreads);
+        let win_rate_low = (win_rate - stats::Z_95 * win_rate_stderr).max(0.0);
+        let win_rate_high = (win_rate + stats::Z_95 * win_rate_stderr).min(1.0);
+        let total_wins = wins.iter().sum::<f64>();
+        Some(Self {
+            num_games: records.len(),
+            wins: total_wins,
+            losses: n - total_wins,
+            total_spread: records.iter().map(|record| record.spread as i64).sum(),
+            mean_spread,
+            mean_spread_stderr,
+            win_rate,
+            win_rate_low,
+            win_rate_high,
+            elo: stats::elo_of(win_rate),
+            elo_low: stats::elo_of(win_rate_low),
+            elo_high: stats::elo_of(win_rate_high),
+            mean_my_bingos: mean_of(|record| record.my_bingos as f64),
+            mean_your_bingos: mean_of(|record| record.your_bingos as f64),
+            mean_moves: mean_of(|record| record.num_moves as f64),
+        })
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} games: {}-{} {}, spread {:.2} +/- {:.2}, win rate {:.2}% ({:.2}% to {:.2}%), elo {:.1} ({:.1} to {:.1})",
+            self.num_games,
+            self.wins,
+            self.losses,
+            self.total_spread,
+            self.mean_spread,
+            stats::Z_95 * self.mean_spread_stderr,
+            100.0 * self.win_rate,
+            100.0 * self.win_rate_low,
+            100.0 * self.win_rate_high,
+            self.elo,
+            self.elo_low,
+            self.elo_high,
+        )
+    }
+}
+
+pub struct ResultsFile {
+    path: String,
+    is_csv: bool,
+    file: std::fs::File,
+}
+
+impl ResultsFile {
+    // With resume, the records already in the file are kept and returned.
+    // Otherwise the file starts over.
+    pub fn open(path: &str, resume: bool) -> error::Returns<(Self, Vec<GameRecord>)> {
+        let is_csv = path.ends_with(".csv");
+        let mut records = Vec::new();
+        let existing = if resume {
+            match std::fs::read_to_string(path) {
+                Ok(s) => s,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(err) => wolges::return_error!(format!("{path}: {err}")),
+            }
+        } else {
+            String::new()
+        };
+        for line in existing.lines() {
+            if line.is_empty() || (is_csv && line == CSV_HEADER) {
+                continue;
+            }
+            records.push(if is_csv {
+                GameRecord::of_csv(line)?
+            } else {
+                serde_json::from_str(line)?
+            });
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|err| format!("{path}: {err}"))?;
+        if !resume {
+            file.set_len(0)?;
+        }
+        if is_csv && existing.is_empty() {
+            writeln!(file, "{CSV_HEADER}")?;
+        }
+        Ok((
+            Self {
+                path: path.to_string(),
+                is_csv,
+                file,
+            },
+            records,
+        ))
+    }
+
+    pub fn append(&mut self, record: &GameRecord) -> error::Returns<()> {
+        if self.is_csv {
+            writeln!(self.file, "{}", record.to_csv())?;
+        } else {
+            writeln!(self.file, "{}", serde_json::to_string(record)?)?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+
+    pub fn write_summary(&self, summary: &Summary) -> error::Returns<()> {
+        std::fs::write(
+            format!("{}.summary.json", self.path),
+            serde_json::to_string_pretty(summary)?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_challenger_moves_again() -> error::Returns<()> {
+        let game_config = game_config::make_english_game_config();
+        let play_reader = alphabet::AlphabetReader::new_for_plays(game_config.alphabet());
+        // the second bingo is challenged off, then the challenger passes.
+        let event_lines = ["8D:RETAINS", "E5:ABALONE", "c", "p", "p"];
+        assert_eq!(
+            is_by_first_player(&event_lines),
+            [true, false, true, true, false]
+        );
+        let record = GameRecord::of_events(
+            "challenged",
+            true,
+            true,
+            (80, 0),
+            &event_lines,
+            &play_reader,
+            game_config.rack_size() as usize,
+        )?;
+        assert_eq!((record.my_bingos, record.your_bingos), (1, 0), "bingos");
+        assert_eq!(
+            (record.my_challenges, record.your_challenges),
+            (1, 0),
+            "challenges"
+        );
+        assert_eq!((record.my_passes, record.your_passes), (1, 1), "passes");
+        assert_eq!(record.num_moves, 4, "moves");
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Writing a generated play in the formats the servers expect.
+
+use super::macondo;
+use wolges::*;
+
+use std::fmt::Write;
+
+// The play as a macondo event. The board is before the play, to fill in the
+// played-through tiles.
+pub fn play_to_event(
+    play: &movegen::Play,
+    game_config: &game_config::GameConfig,
+    board_tiles: &[u8],
+    rack: &[u8],
+) -> macondo::GameEvent {
+    let alphabet = game_config.alphabet();
+    let mut game_event = macondo::GameEvent {
+        rack: format!("{}", alphabet.fmt_rack(rack)),
+        ..Default::default()
+    };
+    match play {
+        movegen::Play::Exchange { tiles } => {
+            if tiles.is_empty() {
+                game_event.set_type(macondo::game_event::Type::Pass);
+            } else {
+                game_event.set_type(macondo::game_event::Type::Exchange);
+                game_event.exchanged = format!("{}", alphabet.fmt_rack(tiles));
+            }
+        }
+        movegen::Play::Place {
+            down,
+            lane,
+            idx,
+            word,
+            score,
+        } => {
+            game_event.set_type(macondo::game_event::Type::TilePlacementMove);
+            let dim = game_config.board_layout().dim();
+
+            let strider = if *down {
+                game_event.row = *idx as i32;
+                game_event.column = *lane as i32;
+                game_event.set_direction(macondo::game_event::Direction::Vertical);
+                game_event.position = format!("{}{}", display::column(*lane), idx + 1);
+                dim.down(*lane)
+            } else {
+                game_event.row = *lane as i32;
+                game_event.column = *idx as i32;
+                game_event.set_direction(macondo::game_event::Direction::Horizontal);
+                game_event.position = format!("{}{}", lane + 1, display::column(*idx));
+                dim.across(*lane)
+            };
+            let mut s = String::new();
+            for (i, &tile) in (*idx..).zip(word.iter()) {
+                let mut shown_tile = tile;
+                if shown_tile == 0 {
+                    shown_tile = board_tiles[strider.at(i)];
+                }
+                s.push_str(alphabet.of_board(shown_tile).unwrap());
+            }
+
+            game_event.played_tiles = s;
+            // Play scores are premultiplied by equity::SCALE (millipoints); the
+            // GameEvent score is in whole points.
+            game_event.score = equity::descale_score(*score);
+        }
+    }
+    game_event
+}
+
+// The play in the bvb format: "p" to pass, "p:TILES" to exchange, or like
+// 9I:SO.UwU to place.
+pub fn play_to_bvb(
+    play: &movegen::Play,
+    alphabet: &alphabet::Alphabet,
+    out: &mut String,
+) -> std::fmt::Result {
+    match play {
+        movegen::Play::Exchange { tiles } => {
+            if tiles.is_empty() {
+                out.push('p');
+            } else {
+                out.push_str("p:");
+                for &tile in tiles.iter() {
+                    out.push_str(alphabet.of_rack(tile).unwrap());
+                }
+            }
+        }
+        movegen::Play::Place {
+            down,
+            lane,
+            idx,
+            word,
+            score: _,
+        } => {
+            if *down {
+                write!(out, "{}{}:", display::column(*lane), idx + 1)?;
+            } else {
+                write!(out, "{}{}:", lane + 1, display::column(*idx))?;
+            }
+            for &tile in word.iter() {
+                if tile == 0 {
+                    out.push('.');
+                } else {
+                    out.push_str(alphabet.of_board(tile).unwrap());
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// A small local http server: POST a BotRequest as json, get the BotResponse
+// back as json, or an AnalysisResponse when posted to /analyze. One request
+// per connection.
+
+use super::{analysis, macondo, transport};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const MAX_HEADER_LEN: usize = 64 << 10;
+const MAX_BODY_LEN: usize = 64 << 20;
+
+// BotRequest::bot_type as its name, like "HASTY_BOT", rather than a number.
+pub mod bot_code {
+    use super::macondo;
+    use serde::Deserialize;
+
+    pub fn serialize<S: serde::Serializer>(
+        bot_code: &i32,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match macondo::bot_request::BotCode::try_from(*bot_code) {
+            Ok(bot_code) => serializer.serialize_str(bot_code.as_str_name()),
+            Err(_) => serializer.serialize_i32(*bot_code),
+        }
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        macondo::bot_request::BotCode::from_str_name(&name)
+            .map(|bot_code| bot_code as i32)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown bot code {name:?}")))
+    }
+}
+
+pub struct HttpTransport {
+    requests: tokio::sync::mpsc::UnboundedReceiver<(transport::RequestResult, HttpResponder)>,
+}
+
+impl HttpTransport {
+    pub async fn bind(addr: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let (requests_tx, requests_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let requests_tx = requests_tx.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = read_request(stream, &requests_tx).await {
+                                println!("http: {err}");
+                            }
+                        });
+                    }
+                    Err(err) => println!("http: cannot accept: {err}"),
+                }
+            }
+        });
+        Ok(Self {
+            requests: requests_rx,
+        })
+    }
+}
+
+impl transport::Transport for HttpTransport {
+    type Responder = HttpResponder;
+
+    async fn receive(&mut self) -> Option<(transport::RequestResult, HttpResponder)> {
+        self.requests.recv().await
+    }
+}
+
+// Reads one request. Only POST is served, at /analyze or any other path. A body that is not a
+// BotRequest is still passed on, so the error is answered like any other.
+async fn read_request(
+    mut stream: tokio::net::TcpStream,
+    requests_tx: &tokio::sync::mpsc::UnboundedSender<(transport::RequestResult, HttpResponder)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut buf = Vec::new();
+    let header_len = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_HEADER_LEN {
+            return write_status(&mut stream, "431 Request Header Fields Too Large").await;
+        }
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            wolges::return_error!("connection closed in header".into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let Ok(header) = std::str::from_utf8(&buf[..header_len]) else {
+        return write_status(&mut stream, "400 Bad Request").await;
+    };
+    let mut lines = header.split("\r\n");
+    let request_line = lines.next().unwrap_or("");
+    let mut request_tokens = request_line.split(' ');
+    if request_tokens.next() != Some("POST") {
+        return write_status(&mut stream, "405 Method Not Allowed").await;
+    }
+    let is_analysis = request_tokens.next() == Some("/analyze");
+    let mut content_len = 0;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            let Ok(len) = value.trim().parse() else {
+                return write_status(&mut stream, "400 Bad Request").await;
+            };
+            content_len = len;
+        }
+    }
+    if content_len > MAX_BODY_LEN {
+        return write_status(&mut stream, "413 Content Too Large").await;
+    }
+    let mut body = buf.split_off(header_len);
+    if body.len() < content_len {
+        let already = body.len();
+        body.resize(content_len, 0);
+        stream.read_exact(&mut body[already..]).await?;
+    }
+    body.truncate(content_len);
+    let bot_req = serde_json::from_slice::<macondo::BotRequest>(&body).map_err(|err| err.into());
+    if requests_tx
+        .send((
+            bot_req,
+            HttpResponder {
+                stream,
+                is_analysis,
+            },
+        ))
+        .is_err()
+    {
+        wolges::return_error!("no longer serving requests".into());
+    }
+    Ok(())
+}
+
+async fn write_status(
+    stream: &mut tokio::net::TcpStream,
+    status: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    stream
+        .write_all(
+            format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .as_bytes(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub struct HttpResponder {
+    stream: tokio::net::TcpStream,
+    is_analysis: bool,
+}
+
+impl HttpResponder {
+    async fn write_json<M: serde::Serialize + Sync>(
+        mut self,
+        msg: &M,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let body = serde_json::to_string(msg)?;
+        self.stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .as_bytes(),
+            )
+            .await?;
+        self.stream.write_all(body.as_bytes()).await?;
+        Ok(())
+    }
+}
+
+impl transport::Responder for HttpResponder {
+    fn wants_analysis(&self) -> bool {
+        self.is_analysis
+    }
+
+    fn must_reply(&self) -> bool {
+        true
+    }
+
+    async fn send(
+        self,
+        bot_resp: macondo::BotResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.write_json(&bot_resp).await
+    }
+
+    async fn send_analysis(
+        self,
+        analysis_resp: analysis::AnalysisResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.write_json(&analysis_resp).await
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// The bot service, and the position handling it shares with the tools around
+// it: rebuilding a position from a game history, checking the plays in it, and
+// writing the bot's own plays back out.
+
+pub mod macondo {
+    #![allow(clippy::derive_partial_eq_without_eq)]
+    // the serde derives read the deprecated fields too.
+    #![allow(deprecated)]
+    include!(concat!(env!("OUT_DIR"), "/macondo.rs"));
+}
+
+pub mod analysis {
+    #![allow(clippy::derive_partial_eq_without_eq)]
+    include!(concat!(env!("OUT_DIR"), "/analysis.rs"));
+}
+
+pub mod encode;
+pub mod http;
+pub mod position;
+pub mod reconstruct;
+pub mod seed;
+pub mod service;
+pub mod sim;
+pub mod stats;
+pub mod strategy;
+pub mod transport;
+
+use wolges::*;
+
+// handles '.' and the equivalent of A-Z, a-z
+pub fn parse_played_tiles(
+    alphabet_reader: &alphabet::AlphabetReader,
+    s: &str,
+    v: &mut Vec<u8>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    v.clear();
+    if !s.is_empty() {
+        v.reserve(s.len());
+        let sb = s.as_bytes();
+        let mut ix = 0;
+        while ix < sb.len() {
+            if let Some((tile, end_ix)) = alphabet_reader.next_tile(sb, ix) {
+                v.push(tile);
+                ix = end_ix;
+            } else if sb[ix] == b'.' {
+                v.push(0);
+                ix += 1;
+            } else {
+                wolges::return_error!(format!("invalid tile after {v:?} in {s:?}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+// handles the equivalent of '?', A-Z
+pub fn parse_rack(
+    alphabet_reader: &alphabet::AlphabetReader,
+    s: &str,
+    v: &mut Vec<u8>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    alphabet_reader.set_word(s, v)
+}
@@ -0,0 +1,466 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Placing the tiles of a play on the board, checking that the play is legal,
+// and putting the unseen tiles in the bag.
+
+use super::{macondo, parse_played_tiles};
+use wolges::*;
+
+use std::str::FromStr;
+
+// Where a play goes and what it places, whichever format it came from.
+pub struct Placement<'a> {
+    pub down: bool,
+    pub lane: i8,
+    pub idx: i8,
+    // in the play format, '.' for each played-through tile.
+    pub played_tiles: &'a str,
+    // checked against the recomputed score, if known.
+    pub option_score: Option<i32>,
+}
+
+impl<'a> Placement<'a> {
+    pub fn of_event(
+        event: &'a macondo::GameEvent,
+    ) -> Result<Placement<'a>, Box<dyn std::error::Error>> {
+        let row = i8::try_from(event.row).map_err(|_| format!("bad row {}", event.row))?;
+        let column =
+            i8::try_from(event.column).map_err(|_| format!("bad column {}", event.column))?;
+        let (down, lane, idx) = match event.direction() {
+            macondo::game_event::Direction::Vertical => (true, column, row),
+            macondo::game_event::Direction::Horizontal => (false, row, column),
+        };
+        Ok(Placement {
+            down,
+            lane,
+            idx,
+            played_tiles: &event.played_tiles,
+            option_score: Some(event.score),
+        })
+    }
+
+    // the bvb format is like 9I:SO.UwU, with no score.
+    pub fn of_bvb(
+        s: &'a str,
+        dim: &matrix::Dim,
+    ) -> Result<Placement<'a>, Box<dyn std::error::Error>> {
+        let (coord_token, played_tiles) = s.split_once(':').ok_or(format!("no : in {s:?}"))?;
+        Self::of_coord(coord_token, played_tiles, dim)
+    }
+
+    // the coord is like 8D across or D8 down.
+    pub fn of_coord(
+        coord_token: &str,
+        played_tiles: &'a str,
+        dim: &matrix::Dim,
+    ) -> Result<Placement<'a>, Box<dyn std::error::Error>> {
+        let coord =
+            parse_coord_token(coord_token, dim).ok_or(format!("invalid coord {coord_token:?}"))?;
+        Ok(Placement {
+            down: coord.down,
+            lane: coord.lane,
+            idx: coord.idx,
+            played_tiles,
+            option_score: None,
+        })
+    }
+}
+
+struct Coord {
+    down: bool,
+    lane: i8,
+    idx: i8,
+}
+
+fn parse_coord_token(coord: &str, dim: &matrix::Dim) -> Option<Coord> {
+    let b = coord.as_bytes();
+    let l1 = b
+        .iter()
+        .position(|c| !c.is_ascii_digit())
+        .unwrap_or(b.len());
+    let dig1 = if l1 != 0 {
+        i8::try_from(usize::from_str(unsafe { std::str::from_utf8_unchecked(&b[..l1]) }).ok()? - 1)
+            .ok()?
+    } else {
+        0
+    };
+    let b = &b[l1..];
+    let l2 = b
+        .iter()
+        .position(|c| !c.is_ascii_alphabetic())
+        .unwrap_or(b.len());
+    if l2 == 0 {
+        return None;
+    }
+    if l1 != 0 && l2 != b.len() {
+        return None;
+    }
+    let alp2 = i8::try_from(display::str_to_column_usize_ignore_case(&b[..l2])?).ok()?;
+    if alp2 >= dim.cols {
+        return None;
+    }
+    if l1 != 0 {
+        if dig1 >= dim.rows {
+            return None;
+        }
+        return Some(Coord {
+            down: false,
+            lane: dig1,
+            idx: alp2,
+        });
+    }
+    let b = &b[l2..];
+    let l3 = b
+        .iter()
+        .position(|c| !c.is_ascii_digit())
+        .unwrap_or(b.len());
+    if l3 != b.len() {
+        return None;
+    }
+    let dig3 = i8::try_from(usize::from_str(unsafe { std::str::from_utf8_unchecked(b) }).ok()? - 1)
+        .ok()?;
+    if dig3 >= dim.rows {
+        return None;
+    }
+    Some(Coord {
+        down: true,
+        lane: alp2,
+        idx: dig3,
+    })
+}
+
+// Keeps its buffers between plays.
+pub struct TilePlacer {
+    game_config: std::sync::Arc<game_config::GameConfig>,
+    play_reader: std::sync::Arc<alphabet::AlphabetReader>,
+    place_tiles_buf: Vec<u8>,
+    jumbled_main_tally: Vec<u8>,
+    jumbled_perpendicular_tally: Vec<u8>,
+}
+
+impl TilePlacer {
+    pub fn new(
+        game_config: std::sync::Arc<game_config::GameConfig>,
+        play_reader: std::sync::Arc<alphabet::AlphabetReader>,
+    ) -> Self {
+        Self {
+            game_config,
+            play_reader,
+            place_tiles_buf: Vec::new(),
+            jumbled_main_tally: Vec::new(),
+            jumbled_perpendicular_tally: Vec::new(),
+        }
+    }
+
+    fn is_jumbled(&self) -> bool {
+        match self.game_config.game_rules() {
+            game_config::GameRules::Classic => false,
+            game_config::GameRules::Jumbled => true,
+        }
+    }
+
+    // Puts the tiles on the board. The first move covers the star, and later
+    // moves connect to the tiles already on the board. The score is recomputed
+    // from the premiums under the newly played tiles, and returned. Words are
+    // not checked.
+    pub fn place(
+        &mut self,
+        board_tiles: &mut [u8],
+        placement: &Placement<'_>,
+    ) -> Result<i32, Box<dyn std::error::Error>> {
+        let board_layout = self.game_config.board_layout();
+        let dim = board_layout.dim();
+        let &Placement {
+            down, lane, idx, ..
+        } = placement;
+        if lane < 0 || lane >= if down { dim.cols } else { dim.rows } {
+            wolges::return_error!(format!("bad lane {lane}"));
+        }
+        if idx < 0 || idx >= if down { dim.rows } else { dim.cols } {
+            wolges::return_error!(format!("bad idx {idx}"));
+        }
+        let strider = dim.lane(down, lane);
+        parse_played_tiles(
+            &self.play_reader,
+            placement.played_tiles,
+            &mut self.place_tiles_buf,
+        )?;
+        let place_tiles_buf = &self.place_tiles_buf;
+        if place_tiles_buf.len() < 2 || !place_tiles_buf.iter().any(|&t| t != 0) {
+            wolges::return_error!("not enough tiles played".into());
+        }
+        if idx > 0 && board_tiles[strider.at(idx - 1)] != 0 {
+            wolges::return_error!("has prefix".into());
+        }
+        let end_idx = idx as usize + place_tiles_buf.len();
+        match end_idx.cmp(&(strider.len() as usize)) {
+            std::cmp::Ordering::Greater => {
+                wolges::return_error!("out of bounds".into());
+            }
+            std::cmp::Ordering::Less => {
+                if board_tiles[strider.at(end_idx as i8)] != 0 {
+                    wolges::return_error!("has suffix".into());
+                }
+            }
+            std::cmp::Ordering::Equal => {}
+        }
+        let board_was_empty = board_tiles.iter().all(|&tile| tile == 0);
+        for (i, &tile) in (idx..).zip(place_tiles_buf.iter()) {
+            let j = strider.at(i);
+            if tile == 0 {
+                if board_tiles[j] == 0 {
+                    wolges::return_error!("playing through vacant board".into());
+                }
+            } else if board_tiles[j] != 0 {
+                wolges::return_error!("board not vacant for non-played-through tile".into());
+            } else {
+                board_tiles[j] = tile;
+            }
+        }
+
+        let alphabet = self.game_config.alphabet();
+        let star_idx =
+            board_layout.star_row() as usize * dim.cols as usize + board_layout.star_col() as usize;
+        let premiums = board_layout.premiums();
+        let tile_score = |board_tile: u8| {
+            if board_tile & 0x80 != 0 {
+                0
+            } else {
+                alphabet.score(board_tile) as i32
+            }
+        };
+        let mut covers_star = false;
+        let mut is_connected = false;
+        let mut num_played = 0i8;
+        let mut main_word_score = 0;
+        let mut main_word_multiplier = 1;
+        let mut perpendicular_words_score = 0;
+        for (i, &tile) in (idx..).zip(place_tiles_buf.iter()) {
+            let j = strider.at(i);
+            if tile == 0 {
+                is_connected = true;
+                main_word_score += tile_score(board_tiles[j]);
+                continue;
+            }
+            covers_star |= j == star_idx;
+            num_played += 1;
+            let premium = &premiums[j];
+            main_word_score += tile_score(tile) * premium.tile_multiplier as i32;
+            main_word_multiplier *= premium.word_multiplier as i32;
+            let perpendicular_strider = dim.lane(!down, i);
+            let mut k = lane;
+            while k > 0 && board_tiles[perpendicular_strider.at(k - 1)] != 0 {
+                k -= 1;
+            }
+            let mut perpendicular_word_len = 0;
+            let mut perpendicular_word_score = 0;
+            for k in k..perpendicular_strider.len() {
+                let perpendicular_tile = board_tiles[perpendicular_strider.at(k)];
+                if perpendicular_tile == 0 {
+                    break;
+                }
+                perpendicular_word_len += 1;
+                perpendicular_word_score += if k == lane {
+                    tile_score(perpendicular_tile) * premium.tile_multiplier as i32
+                } else {
+                    tile_score(perpendicular_tile)
+                };
+            }
+            if perpendicular_word_len > 1 {
+                is_connected = true;
+                perpendicular_words_score +=
+                    perpendicular_word_score * premium.word_multiplier as i32;
+            }
+        }
+        if board_was_empty {
+            if !covers_star {
+                wolges::return_error!("first move does not cover the star".into());
+            }
+        } else if !is_connected {
+            wolges::return_error!("not connected to the tiles on board".into());
+        }
+        let score = main_word_score * main_word_multiplier
+            + perpendicular_words_score
+            + self.game_config.num_played_bonus(num_played);
+        if let Some(expected_score) = placement.option_score
+            && score != expected_score
+        {
+            wolges::return_error!(format!("scored {expected_score} instead of {score}"));
+        }
+        Ok(score)
+    }
+
+    // Whether every word formed by the placement is in the kwg. The tiles must
+    // already be on the board.
+    pub fn forms_valid_words<N: kwg::Node>(
+        &mut self,
+        board_tiles: &[u8],
+        placement: &Placement<'_>,
+        kwg: &kwg::Kwg<N>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let is_jumbled = self.is_jumbled();
+        let alphabet = self.game_config.alphabet();
+        let dim = self.game_config.board_layout().dim();
+        let &Placement {
+            down, lane, idx, ..
+        } = placement;
+        let strider = dim.lane(down, lane);
+        parse_played_tiles(
+            &self.play_reader,
+            placement.played_tiles,
+            &mut self.place_tiles_buf,
+        )?;
+        let mut p_main = 0; // dawg
+        let main_tally = &mut self.jumbled_main_tally;
+        if is_jumbled {
+            main_tally.clear();
+            main_tally.resize(alphabet.len() as usize, 0);
+        }
+        for (i, &tile) in (idx..).zip(self.place_tiles_buf.iter()) {
+            let b = board_tiles[strider.at(i)];
+            if is_jumbled {
+                main_tally[(b & 0x7f) as usize] += 1;
+            } else {
+                p_main = kwg.seek(p_main, b & 0x7f);
+            }
+            if tile != 0 {
+                let perpendicular_strider = dim.lane(!down, i);
+                let mut j = lane;
+                while j > 0 && board_tiles[perpendicular_strider.at(j - 1)] != 0 {
+                    j -= 1;
+                }
+                let perpendicular_strider_len = perpendicular_strider.len();
+                if j < lane
+                    || (j + 1 < perpendicular_strider_len
+                        && board_tiles[perpendicular_strider.at(j + 1)] != 0)
+                {
+                    let mut p_perpendicular = 0;
+                    let perpendicular_tally = &mut self.jumbled_perpendicular_tally;
+                    if is_jumbled {
+                        perpendicular_tally.clear();
+                        perpendicular_tally.resize(alphabet.len() as usize, 0);
+                    }
+                    for j in j..perpendicular_strider_len {
+                        let perpendicular_tile = board_tiles[perpendicular_strider.at(j)];
+                        if perpendicular_tile == 0 {
+                            break;
+                        }
+                        if is_jumbled {
+                            perpendicular_tally[(perpendicular_tile & 0x7f) as usize] += 1;
+                        } else {
+                            p_perpendicular = kwg.seek(p_perpendicular, perpendicular_tile & 0x7f);
+                        }
+                    }
+                    if if is_jumbled {
+                        !kwg.accepts_alpha(perpendicular_tally)
+                    } else {
+                        p_perpendicular < 0 || !kwg[p_perpendicular].accepts()
+                    } {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Ok(if is_jumbled {
+            kwg.accepts_alpha(main_tally)
+        } else {
+            p_main >= 0 && kwg[p_main].accepts()
+        })
+    }
+}
+
+// Puts every tile that is not on the board or on a rack in the bag, in tile
+// order, for the caller to shuffle. An error if the board and the racks have
+// more of a tile than there is.
+pub fn fill_bag(
+    game_config: &game_config::GameConfig,
+    game_state: &mut game_state::GameState,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let alphabet = game_config.alphabet();
+    let mut available_tally = (0..alphabet.len())
+        .map(|tile| alphabet.freq(tile))
+        .collect::<Vec<_>>();
+    for (player_idx, player) in game_state.players.iter().enumerate() {
+        for &tile in player.rack.iter() {
+            match available_tally.get_mut(tile as usize) {
+                Some(count) if *count > 0 => *count -= 1,
+                Some(_) => {
+                    wolges::return_error!(format!(
+                        "rack of p{player_idx} has too many of tile {tile}"
+                    ))
+                }
+                None => {
+                    wolges::return_error!(format!("rack of p{player_idx} has invalid tile {tile}"))
+                }
+            }
+        }
+    }
+    for &board_tile in game_state.board_tiles.iter() {
+        if board_tile != 0 {
+            let tile = board_tile & !((board_tile as i8) >> 7) as u8;
+            match available_tally.get_mut(tile as usize) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => wolges::return_error!(format!("board has too many of tile {tile}")),
+            }
+        }
+    }
+    game_state.bag.set_from_iter(
+        (0u8..)
+            .zip(available_tally.iter())
+            .flat_map(|(tile, &count)| std::iter::repeat_n(tile, count as usize)),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn english_tile_placer() -> TilePlacer {
+        let game_config = game_config::make_english_game_config();
+        let play_reader = alphabet::AlphabetReader::new_for_plays(game_config.alphabet());
+        TilePlacer::new(
+            std::sync::Arc::new(game_config),
+            std::sync::Arc::new(play_reader),
+        )
+    }
+
+    fn place(
+        tile_placer: &mut TilePlacer,
+        board_tiles: &mut [u8],
+        coord_token: &str,
+        played_tiles: &str,
+    ) -> Result<i32, Box<dyn std::error::Error>> {
+        let dim = tile_placer.game_config.board_layout().dim();
+        let placement = Placement::of_coord(coord_token, played_tiles, &dim)?;
+        tile_placer.place(board_tiles, &placement)
+    }
+
+    #[test]
+    fn the_first_move_covers_the_star() -> error::Returns<()> {
+        let mut tile_placer = english_tile_placer();
+        let mut board_tiles = vec![0u8; 15 * 15];
+        let Err(err) = place(&mut tile_placer, &mut board_tiles, "1A", "CAT") else {
+            panic!("placed off the star");
+        };
+        assert_eq!(err.to_string(), "first move does not cover the star");
+        board_tiles.fill(0);
+        // on the double word star.
+        assert_eq!(place(&mut tile_placer, &mut board_tiles, "8G", "CAT")?, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn later_moves_connect() -> error::Returns<()> {
+        let mut tile_placer = english_tile_placer();
+        let mut board_tiles = vec![0u8; 15 * 15];
+        place(&mut tile_placer, &mut board_tiles, "8G", "CAT")?;
+        let Err(err) = place(&mut tile_placer, &mut board_tiles, "1A", "AT") else {
+            panic!("placed away from the tiles on board");
+        };
+        assert_eq!(err.to_string(), "not connected to the tiles on board");
+        // through the A of CAT.
+        place(&mut tile_placer, &mut board_tiles, "H7", "T.")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Replaying a game history event by event, checking that it adds up, and
+// rebuilding the position after it.
+
+use super::{macondo, parse_rack, position};
+use wolges::*;
+
+// Which player made each event, and whose turn it is after the last event.
+pub struct PlayerIndexes {
+    pub of_event: Vec<usize>,
+    pub on_turn: usize,
+}
+
+// Events carry a player_index, and older histories also a nickname. A nickname
+// shared by several players, or a player_index of 0 with no nickname (it may
+// just have been left unset), is resolved by turn order: moves alternate, and
+// phony returns, challenge bonuses and end rack points go to whoever moved
+// last. Moves out of turn are an error, as is anything that cannot be
+// resolved, rather than guessing the wrong racks.
+#[expect(deprecated)]
+pub fn determine_player_indexes(
+    game_history: &macondo::GameHistory,
+) -> Result<PlayerIndexes, Box<dyn std::error::Error>> {
+    let num_players = game_history.players.len();
+    let mut of_event = Vec::with_capacity(game_history.events.len());
+    let mut on_turn = game_history.second_went_first as usize % num_players;
+    let mut last_mover = None;
+    let mut turn_is_known = false;
+    let mut candidates = Vec::new();
+    for (i, event) in game_history.events.iter().enumerate() {
+        let takes_turn = match event.r#type() {
+            macondo::game_event::Type::TilePlacementMove
+            | macondo::game_event::Type::Pass
+            | macondo::game_event::Type::Exchange
+            | macondo::game_event::Type::UnsuccessfulChallengeTurnLoss => Some(true),
+            macondo::game_event::Type::PhonyTilesReturned
+            | macondo::game_event::Type::ChallengeBonus
+            | macondo::game_event::Type::EndRackPts => Some(false),
+            macondo::game_event::Type::TimePenalty
+            | macondo::game_event::Type::EndRackPenalty
+            | macondo::game_event::Type::Challenge => None,
+        };
+        let expected = match takes_turn {
+            Some(true) => Some(on_turn),
+            Some(false) => last_mover,
+            None => None,
+        };
+        let player_idx = if event.nickname.is_empty() {
+            match expected {
+                Some(player_idx) if event.player_index == 0 => player_idx,
+                _ => event.player_index as usize,
+            }
+        } else {
+            candidates.clear();
+            candidates.extend(
+                (0..num_players).filter(|&p| game_history.players[p].nickname == event.nickname),
+            );
+            match candidates[..] {
+                [] => wolges::return_error!(format!(
+                    "event {i} is by unknown player {:?}",
+                    event.nickname
+                )),
+                [player_idx] => player_idx,
+                _ => match expected {
+                    Some(player_idx) if candidates.contains(&player_idx) => player_idx,
+                    _ => wolges::return_error!(format!(
+                        "event {i} could be by any of players {candidates:?}"
+                    )),
+                },
+            }
+        };
+        if player_idx >= num_players {
+            wolges::return_error!(format!("event {i} is by player {player_idx}"));
+        }
+        // the first move decides who went first.
+        if let Some(expected_player_idx) = expected
+            && player_idx != expected_player_idx
+            && (takes_turn == Some(false) || turn_is_known)
+        {
+            wolges::return_error!(format!(
+                "event {i} is by player {player_idx}, expected player {expected_player_idx}"
+            ));
+        }
+        if takes_turn == Some(true) {
+            last_mover = Some(player_idx);
+            on_turn = (player_idx + 1) % num_players;
+            turn_is_known = true;
+        }
+        of_event.push(player_idx);
+    }
+    Ok(PlayerIndexes { of_event, on_turn })
+}
+
+pub struct Reconstruction {
+    pub player_indexes: PlayerIndexes,
+    // everyone's score after the last event.
+    pub scores: Vec<i32>,
+    // the tile placements still on the board, in order. a placement whose
+    // tiles were returned as phony is not here.
+    pub placements: Vec<usize>,
+}
+
+// Each event changes its player's score by its own field: the score of a
+// placement, the bonus for a challenged valid play, the end rack points, and
+// minus the lost score for phonies returned, time penalties and the tiles left
+// on the rack. Passes, exchanges, challenges and losing a turn to a challenge
+// score nothing. Every event's cumulative must match the replayed score, and
+// nobody moves after the game is over. A history that does not add up is an
+// error listing every discrepancy, so the bot does not play from a wrong score.
+pub fn reconstruct(
+    game_history: &macondo::GameHistory,
+) -> Result<Reconstruction, Box<dyn std::error::Error>> {
+    let player_indexes = determine_player_indexes(game_history)?;
+    let mut scores = vec![0; game_history.players.len()];
+    let mut placements = Vec::new();
+    let mut discrepancies = Vec::new();
+    // the latest placement that can still be challenged off.
+    let mut option_challengeable = None;
+    let mut option_game_over_at = None;
+    for (i, event) in game_history.events.iter().enumerate() {
+        let player_idx = player_indexes.of_event[i];
+        let mut check_not_negative = |name: &str, value: i32| {
+            if value < 0 {
+                discrepancies.push(format!("event {i} has {name} {value}"));
+            }
+        };
+        let delta = match event.r#type() {
+            macondo::game_event::Type::TilePlacementMove => {
+                check_not_negative("score", event.score);
+                event.score
+            }
+            macondo::game_event::Type::PhonyTilesReturned => {
+                check_not_negative("lost score", event.lost_score);
+                -event.lost_score
+            }
+            macondo::game_event::Type::ChallengeBonus => {
+                check_not_negative("bonus", event.bonus);
+                event.bonus
+            }
+            macondo::game_event::Type::EndRackPts => {
+                check_not_negative("end rack points", event.end_rack_points);
+                event.end_rack_points
+            }
+            macondo::game_event::Type::TimePenalty | macondo::game_event::Type::EndRackPenalty => {
+                check_not_negative("lost score", event.lost_score);
+                -event.lost_score
+            }
+            macondo::game_event::Type::Pass
+            | macondo::game_event::Type::Exchange
+            | macondo::game_event::Type::UnsuccessfulChallengeTurnLoss
+            | macondo::game_event::Type::Challenge => 0,
+        };
+        match event.r#type() {
+            macondo::game_event::Type::TilePlacementMove => {
+                option_challengeable = Some(placements.len());
+                placements.push(i);
+            }
+            macondo::game_event::Type::PhonyTilesReturned => match option_challengeable.take() {
+                Some(placement_idx) => {
+                    let placement = placements.remove(placement_idx);
+                    let placement_event = &game_history.events[placement];
+                    if player_indexes.of_event[placement] != player_idx {
+                        discrepancies.push(format!(
+                            "event {i} returns the tiles of event {placement} by another player"
+                        ));
+                    } else if event.lost_score != placement_event.score {
+                        discrepancies.push(format!(
+                            "event {i} loses {}, event {placement} scored {}",
+                            event.lost_score, placement_event.score
+                        ));
+                    }
+                }
+                None => discrepancies.push(format!("event {i} returns no tiles")),
+            },
+            macondo::game_event::Type::ChallengeBonus => {
+                if option_challengeable.is_none() {
+                    discrepancies.push(format!("event {i} is a bonus for no placement"));
+                }
+            }
+            macondo::game_event::Type::Pass
+            | macondo::game_event::Type::Exchange
+            | macondo::game_event::Type::UnsuccessfulChallengeTurnLoss => {
+                option_challengeable = None;
+            }
+            macondo::game_event::Type::EndRackPts | macondo::game_event::Type::EndRackPenalty => {
+                // the final play may still be challenged.
+                option_game_over_at.get_or_insert(i);
+            }
+            macondo::game_event::Type::TimePenalty | macondo::game_event::Type::Challenge => {}
+        }
+        if let Some(game_over_at) = option_game_over_at
+            && matches!(
+                event.r#type(),
+                macondo::game_event::Type::TilePlacementMove
+                    | macondo::game_event::Type::Pass
+                    | macondo::game_event::Type::Exchange
+            )
+        {
+            discrepancies.push(format!(
+                "event {i} is after the game ended at event {game_over_at}"
+            ));
+        }
+        let expected = scores[player_idx] + delta;
+        if event.cumulative != expected {
+            discrepancies.push(format!(
+                "event {i} by p{player_idx} has cumulative {}, expected {expected}",
+                event.cumulative
+            ));
+        }
+        // carry on from the recorded score so one mistake is reported once.
+        scores[player_idx] = event.cumulative;
+    }
+    if !discrepancies.is_empty() {
+        for discrepancy in discrepancies.iter() {
+            println!("game {}: {discrepancy}", game_history.uid);
+        }
+        wolges::return_error!(format!(
+            "history does not add up: {}",
+            discrepancies.join("; ")
+        ));
+    }
+    Ok(Reconstruction {
+        player_indexes,
+        scores,
+        placements,
+    })
+}
+
+impl Reconstruction {
+    // Puts the tiles of every placement before event end_idx on the board.
+    pub fn place_tiles_before(
+        &self,
+        game_history: &macondo::GameHistory,
+        tile_placer: &mut position::TilePlacer,
+        board_tiles: &mut [u8],
+        end_idx: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for &i in self.placements.iter().take_while(|&&i| i < end_idx) {
+            tile_placer.place(
+                board_tiles,
+                &position::Placement::of_event(&game_history.events[i])?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// The game configs are for two players, so make room for everyone.
+pub fn set_num_players(game_state: &mut game_state::GameState, num_players: usize) {
+    if game_state.players.len() != num_players {
+        game_state.players =
+            std::iter::repeat_n(game_state.players[0].clone(), num_players).collect();
+    }
+}
+
+// The position a request is about, rebuilt from its game history.
+pub struct RebuiltPosition {
+    pub reconstruction: Reconstruction,
+    // whether the latest play, if it can still be challenged, forms only valid
+    // words, and only common words.
+    pub is_valid: bool,
+    pub is_common: bool,
+}
+
+// Places the tiles, loads the racks and scores, and fills and shuffles the bag.
+#[expect(clippy::too_many_arguments)]
+pub fn rebuild_position<N: kwg::Node, R: rand::Rng>(
+    game_history: &macondo::GameHistory,
+    game_state: &mut game_state::GameState,
+    tile_placer: &mut position::TilePlacer,
+    kwg: &kwg::Kwg<N>,
+    option_common_word_kwg: Option<&kwg::Kwg<N>>,
+    game_config: &game_config::GameConfig,
+    is_jumbled: bool,
+    rack_reader: &alphabet::AlphabetReader,
+    rng: &mut R,
+) -> Result<RebuiltPosition, Box<dyn std::error::Error>> {
+    let reconstruction = reconstruct(game_history)?;
+    let num_players = game_history.players.len();
+    set_num_players(game_state, num_players);
+    game_state.reset();
+    for (player, &score) in game_state
+        .players
+        .iter_mut()
+        .zip(reconstruction.scores.iter())
+    {
+        player.score = score;
+    }
+    let option_last_tile_placement = reconstruction.placements.last().copied();
+    reconstruction.place_tiles_before(
+        game_history,
+        tile_placer,
+        &mut game_state.board_tiles,
+        option_last_tile_placement.unwrap_or(game_history.events.len()),
+    )?;
+    let mut is_valid = true;
+    let mut is_common = true;
+    if let Some(last_tile_placement) = option_last_tile_placement {
+        let placement = position::Placement::of_event(&game_history.events[last_tile_placement])?;
+        tile_placer.place(&mut game_state.board_tiles, &placement)?;
+        // only the latest play can still be challenged.
+        if last_tile_placement == game_history.events.len() - 1 {
+            is_valid = tile_placer.forms_valid_words(&game_state.board_tiles, &placement, kwg)?;
+            if let Some(common_word_kwg) = option_common_word_kwg
+                && !is_jumbled
+            {
+                is_common = tile_placer.forms_valid_words(
+                    &game_state.board_tiles,
+                    &placement,
+                    common_word_kwg,
+                )?;
+            }
+        }
+    }
+
+    // load the racks, every known rack is not in the bag.
+    if game_history.last_known_racks.len() != num_players {
+        wolges::return_error!("need a rack for every player".into());
+    }
+    for player_idx in 0..num_players {
+        let rack = &mut game_state.players[player_idx].rack;
+        parse_rack(
+            rack_reader,
+            &game_history.last_known_racks[player_idx],
+            rack,
+        )?;
+        if rack.len() > game_config.rack_size() as usize {
+            wolges::return_error!(format!("rack of p{player_idx} is too long"));
+        }
+    }
+    position::fill_bag(game_config, game_state)?;
+    game_state.bag.shuffle(rng);
+
+    game_state.turn = reconstruction.player_indexes.on_turn as u8;
+    Ok(RebuiltPosition {
+        reconstruction,
+        is_valid,
+        is_common,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(events: Vec<macondo::GameEvent>) -> macondo::GameHistory {
+        macondo::GameHistory {
+            players: vec![Default::default(); 2],
+            events,
+            ..Default::default()
+        }
+    }
+
+    fn event(
+        r#type: macondo::game_event::Type,
+        player_index: u32,
+        cumulative: i32,
+    ) -> macondo::GameEvent {
+        let mut game_event = macondo::GameEvent {
+            player_index,
+            cumulative,
+            ..Default::default()
+        };
+        game_event.set_type(r#type);
+        game_event
+    }
+
+    fn placement(player_index: u32, score: i32, cumulative: i32) -> macondo::GameEvent {
+        macondo::GameEvent {
+            score,
+            ..event(
+                macondo::game_event::Type::TilePlacementMove,
+                player_index,
+                cumulative,
+            )
+        }
+    }
+
+    fn error_of(game_history: &macondo::GameHistory) -> String {
+        match reconstruct(game_history) {
+            Ok(_) => panic!("reconstructed {:?}", game_history.events),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    #[expect(deprecated)]
+    fn a_shared_nickname_goes_by_turn_order() -> error::Returns<()> {
+        let mut game_history = history(vec![placement(0, 20, 20), placement(0, 10, 10)]);
+        for player in game_history.players.iter_mut() {
+            player.nickname = "bot".into();
+        }
+        for game_event in game_history.events.iter_mut() {
+            game_event.nickname = "bot".into();
+        }
+        let player_indexes = determine_player_indexes(&game_history)?;
+        assert_eq!(player_indexes.of_event, [0, 1]);
+        assert_eq!(player_indexes.on_turn, 0);
+        // nobody's turn says who took the time penalty.
+        let mut time_penalty = event(macondo::game_event::Type::TimePenalty, 0, 10);
+        time_penalty.nickname = "bot".into();
+        game_history.events.push(time_penalty);
+        let Err(err) = determine_player_indexes(&game_history) else {
+            panic!("guessed who took the time penalty");
+        };
+        assert_eq!(err.to_string(), "event 2 could be by any of players [0, 1]");
+        Ok(())
+    }
+
+    #[test]
+    #[expect(deprecated)]
+    fn player_index_0_without_a_nickname_goes_by_turn_order() -> error::Returns<()> {
+        let mut game_history = history(vec![
+            placement(0, 20, 20),
+            placement(0, 30, 30),
+            macondo::GameEvent {
+                lost_score: 30,
+                ..event(macondo::game_event::Type::PhonyTilesReturned, 0, 0)
+            },
+        ]);
+        game_history.second_went_first = true;
+        let reconstruction = reconstruct(&game_history)?;
+        assert_eq!(reconstruction.player_indexes.of_event, [1, 0, 0]);
+        assert_eq!(reconstruction.player_indexes.on_turn, 1);
+        assert_eq!(reconstruction.scores, [0, 20]);
+        // the phony is off the board.
+        assert_eq!(reconstruction.placements, [0]);
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_player_who_moved_returns_the_phony() {
+        let game_history = history(vec![
+            placement(0, 20, 20),
+            macondo::GameEvent {
+                lost_score: 20,
+                ..event(macondo::game_event::Type::PhonyTilesReturned, 1, -20)
+            },
+        ]);
+        assert_eq!(
+            error_of(&game_history),
+            "event 1 is by player 1, expected player 0"
+        );
+    }
+
+    #[test]
+    fn the_cumulatives_add_up() {
+        let game_history = history(vec![placement(0, 20, 20), placement(1, 10, 12)]);
+        assert_eq!(
+            error_of(&game_history),
+            "history does not add up: event 1 by p1 has cumulative 12, expected 10"
+        );
+    }
+
+    #[test]
+    fn nobody_moves_after_the_game_ends() {
+        let game_history = history(vec![
+            placement(0, 20, 20),
+            placement(1, 10, 10),
+            macondo::GameEvent {
+                end_rack_points: 4,
+                ..event(macondo::game_event::Type::EndRackPts, 1, 14)
+            },
+            event(macondo::game_event::Type::Pass, 0, 20),
+        ]);
+        assert_eq!(
+            error_of(&game_history),
+            "history does not add up: event 3 is after the game ended at event 2"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Seeds for the random number generators. A request for the same game at the
+// same event count gets the same seed, so a retried request gets the same move
+// and a logged seed replays a move exactly.
+
+pub struct Seeder {
+    master_seed: u64,
+    option_fixed_seed: Option<u64>,
+}
+
+impl Seeder {
+    // OMGBOT_SEED sets the master seed, else it is random. OMGBOT_FIXED_SEED
+    // makes every request use exactly that seed, as logged with a move.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let master_seed = match std::env::var("OMGBOT_SEED") {
+            Ok(s) => s
+                .parse()
+                .map_err(|err| format!("bad OMGBOT_SEED {s:?}: {err}"))?,
+            Err(_) => rand::random(),
+        };
+        let option_fixed_seed = match std::env::var("OMGBOT_FIXED_SEED") {
+            Ok(s) => Some(
+                s.parse()
+                    .map_err(|err| format!("bad OMGBOT_FIXED_SEED {s:?}: {err}"))?,
+            ),
+            Err(_) => None,
+        };
+        Ok(Self {
+            master_seed,
+            option_fixed_seed,
+        })
+    }
+
+    pub fn master_seed(&self) -> u64 {
+        self.master_seed
+    }
+
+    pub fn request_seed(&self, game_id: &str, num_events: usize) -> u64 {
+        if let Some(fixed_seed) = self.option_fixed_seed {
+            return fixed_seed;
+        }
+        fnv1a(
+            self.master_seed
+                .to_le_bytes()
+                .iter()
+                .chain(game_id.as_bytes())
+                .chain((num_events as u64).to_le_bytes().iter()),
+        )
+    }
+}
+
+// fnv-1a, because std's hasher may change between rust versions.
+pub fn fnv1a<'a>(bytes: impl Iterator<Item = &'a u8>) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &b in bytes {
+        hash = (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,1951 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// The bot service: the lexicons loaded at startup, and the loop that answers
+// bot requests from a transport.
+
+mod analyze;
+pub mod book;
+mod delay;
+pub mod engine;
+mod eval_cache;
+mod pre_endgame;
+mod report;
+
+use super::{
+    analysis, encode, macondo, parse_played_tiles, parse_rack, position, reconstruct, seed, sim,
+    transport,
+};
+use prost::Message;
+use rand::prelude::*;
+use wolges::*;
+
+enum ArcKwgEither {
+    Node22(std::sync::Arc<kwg::Kwg<kwg::Node22>>),
+    Node24(std::sync::Arc<kwg::Kwg<kwg::Node24>>),
+}
+
+fn each_word_raw<F: FnMut(&[u8]), N: kwg::Node>(g: &kwg::Kwg<N>, f: F) {
+    struct Env<'a, F: FnMut(&[u8]), N: kwg::Node> {
+        g: &'a kwg::Kwg<N>,
+        v: &'a mut Vec<u8>,
+        f: F,
+    }
+    fn iter<F: FnMut(&[u8]), N: kwg::Node>(env: &mut Env<'_, F, N>, mut p: i32) {
+        loop {
+            let t = env.g[p].tile();
+            env.v.push(t);
+            if env.g[p].accepts() {
+                (env.f)(env.v);
+            }
+            if env.g[p].arc_index() != 0 {
+                iter(env, env.g[p].arc_index());
+            }
+            env.v.pop();
+            if env.g[p].is_end() {
+                break;
+            }
+            p += 1;
+        }
+    }
+    iter(
+        &mut Env {
+            g,
+            v: &mut Vec::new(),
+            f,
+        },
+        g[0].arc_index(),
+    );
+}
+
+fn each_word<F: FnMut(&[u8])>(g: &ArcKwgEither, f: F) {
+    match g {
+        ArcKwgEither::Node22(g) => each_word_raw(g, f),
+        ArcKwgEither::Node24(g) => each_word_raw(g, f),
+    }
+}
+
+struct ElucubrateArguments<'a, N: kwg::Node + Send> {
+    bot_req: Box<macondo::BotRequest>,
+    tilter: Option<wolges::move_filter::Tilt<'a>>,
+    game_state: game_state::GameState,
+    tile_placer: position::TilePlacer,
+    kwg: &'a std::sync::Arc<kwg::Kwg<N>>,
+    game_config: &'a std::sync::Arc<game_config::GameConfig>,
+    klv: &'a std::sync::Arc<klv::Klv<kwg::Node22>>,
+    noleave_klv: &'a std::sync::Arc<klv::Klv<kwg::Node22>>,
+    move_generator: movegen::KurniaMoveGenerator,
+    is_jumbled: bool,
+    rack_reader: &'a std::sync::Arc<alphabet::AlphabetReader>,
+    option_common_word_kwg: Option<std::sync::Arc<kwg::Kwg<N>>>,
+    option_opening_book: Option<std::sync::Arc<book::OpeningBook>>,
+    pre_endgame_max_bag: usize,
+    rng: &'a mut rand::rngs::ChaCha20Rng,
+}
+
+// What challenging a valid play costs the challenger.
+#[derive(Clone, Copy, PartialEq)]
+enum ChallengePenalty {
+    Nothing,
+    LoseTurn,
+    OpponentBonus(i32),
+    LoseGame,
+}
+
+impl std::fmt::Display for ChallengePenalty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nothing => write!(f, "nothing"),
+            Self::LoseTurn => write!(f, "a turn"),
+            Self::OpponentBonus(bonus) => write!(f, "{bonus} points to the opponent"),
+            Self::LoseGame => write!(f, "the game"),
+        }
+    }
+}
+
+// None if phonies cannot be played at all. Once the opponent has gone out,
+// the game ends after the challenge, so there is no turn left to lose.
+fn challenge_penalty(
+    challenge_rule: macondo::ChallengeRule,
+    opponent_went_out: bool,
+) -> Option<ChallengePenalty> {
+    match challenge_rule {
+        macondo::ChallengeRule::Void => None,
+        macondo::ChallengeRule::Single => Some(ChallengePenalty::Nothing),
+        macondo::ChallengeRule::Double => Some(if opponent_went_out {
+            ChallengePenalty::Nothing
+        } else {
+            ChallengePenalty::LoseTurn
+        }),
+        macondo::ChallengeRule::FivePoint => Some(ChallengePenalty::OpponentBonus(5)),
+        macondo::ChallengeRule::TenPoint => Some(ChallengePenalty::OpponentBonus(10)),
+        macondo::ChallengeRule::Triple => Some(ChallengePenalty::LoseGame),
+    }
+}
+
+// Tilt bots do not challenge perfectly. Returns the probabilities of letting
+// a phony stand, and of challenging a valid word that is not a common word
+// (when a wrong challenge costs nothing, less when it gives a bonus).
+fn challenge_mistake_probabilities(bot_level: i8) -> (f64, f64) {
+    match bot_level {
+        1 => (0.5, 0.3),
+        2 => (0.35, 0.2),
+        3 => (0.2, 0.1),
+        4 => (0.1, 0.05),
+        _ => (0.05, 0.02),
+    }
+}
+
+// Only draws from the rng for a mistake that can happen, so bots that never
+// make it draw the same as if there were no such mistake.
+fn makes_mistake<R: rand::Rng>(rng: &mut R, p: f64) -> bool {
+    p > 0.0 && rng.random_bool(p)
+}
+
+// below this much time on the clock, the bot replies as fast as it can.
+const PANIC_MILLIS: i32 = 10_000;
+// simming needs at least this much time for the move, else it plays hastily.
+const SIM_MIN_MILLIS_FOR_MOVE: i32 = 10_000;
+
+// Picks with the simmer from wolges on a thread of its own, as it cannot be
+// told to stop. If it has not picked by the deadline, false is returned and
+// move_generator is untouched; the simmer finishes unwatched. game_state is for
+// the player on turn.
+fn sim_until<N: kwg::Node + Send + Sync + 'static>(
+    game_config: &std::sync::Arc<game_config::GameConfig>,
+    kwg: &std::sync::Arc<kwg::Kwg<N>>,
+    klv: &std::sync::Arc<klv::Klv<kwg::Node22>>,
+    game_state: &game_state::GameState,
+    move_generator: &mut movegen::KurniaMoveGenerator,
+    option_deadline: Option<std::time::Instant>,
+) -> bool {
+    let game_config = std::sync::Arc::clone(game_config);
+    let kwg = std::sync::Arc::clone(kwg);
+    let klv = std::sync::Arc::clone(klv);
+    let sim_state = game_state.clone();
+    let (plays_tx, plays_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut move_generator = movegen::KurniaMoveGenerator::new(&game_config);
+        move_picker::MovePicker::Simmer(move_picker::Simmer::new(&game_config, &kwg, &klv))
+            .pick_a_move(
+                &mut move_filter::GenMoves::Unfiltered,
+                &mut move_generator,
+                &movegen::BoardSnapshot {
+                    board_tiles: &sim_state.board_tiles,
+                    game_config: &game_config,
+                    kwg: &kwg,
+                    klv: &klv,
+                },
+                &sim_state,
+                &sim_state.current_player().rack,
+            );
+        // nobody is listening after the deadline.
+        let _ = plays_tx.send(move_generator.plays);
+    });
+    let plays_result = match option_deadline {
+        Some(deadline) => plays_rx
+            .recv_timeout(deadline.saturating_duration_since(std::time::Instant::now()))
+            .map_err(|err| err.to_string()),
+        None => plays_rx.recv().map_err(|err| err.to_string()),
+    };
+    match plays_result {
+        Ok(plays) => {
+            move_generator.plays = plays;
+            true
+        }
+        Err(err) => {
+            println!("not simming after all: {err}");
+            false
+        }
+    }
+}
+
+// How much time the bot may use for this move.
+struct TimeBudget {
+    millis_remaining: i32,
+    millis_increment: i32,
+    millis_for_move: i32,
+}
+
+// Each event records its player's clock after the event, which goes negative
+// once they are over time. The increment is not in the history, so it is
+// estimated from the time the player gained on a move. None if the game is not
+// timed, that is, none of the player's events has a clock.
+fn time_budget(
+    game_history: &macondo::GameHistory,
+    player_indexes: &reconstruct::PlayerIndexes,
+    player_idx: usize,
+    num_moves_left: i32,
+) -> Option<TimeBudget> {
+    let mut option_millis_remaining = None;
+    let mut is_timed = false;
+    let mut millis_increment = 0;
+    for (event, &event_player_idx) in game_history
+        .events
+        .iter()
+        .zip(player_indexes.of_event.iter())
+    {
+        if event_player_idx == player_idx {
+            is_timed |= event.millis_remaining != 0;
+            if let Some(previous_millis_remaining) = option_millis_remaining
+                && previous_millis_remaining > 0
+                && event.millis_remaining > 0
+            {
+                millis_increment =
+                    millis_increment.max(event.millis_remaining - previous_millis_remaining);
+            }
+            option_millis_remaining = Some(event.millis_remaining);
+        }
+    }
+    if !is_timed {
+        return None;
+    }
+    let millis_remaining = option_millis_remaining?;
+    let millis_for_move = if millis_remaining <= PANIC_MILLIS {
+        0
+    } else {
+        (millis_remaining - PANIC_MILLIS) / num_moves_left.max(1) + millis_increment * 4 / 5
+    };
+    Some(TimeBudget {
+        millis_remaining,
+        millis_increment,
+        millis_for_move,
+    })
+}
+
+enum OmgBotType {
+    Unfiltered,
+    Tilt(i8),
+    Sim,
+}
+
+// whether the bot plays only common words, and how it picks a move.
+fn omg_bot_type(bot_code: macondo::bot_request::BotCode) -> (bool, OmgBotType) {
+    match bot_code {
+        macondo::bot_request::BotCode::HastyBot => (false, OmgBotType::Unfiltered),
+        macondo::bot_request::BotCode::Level1CommonWordBot => (true, OmgBotType::Tilt(1)),
+        macondo::bot_request::BotCode::Level2CommonWordBot => (true, OmgBotType::Tilt(2)),
+        macondo::bot_request::BotCode::Level3CommonWordBot => (true, OmgBotType::Tilt(3)),
+        macondo::bot_request::BotCode::Level4CommonWordBot => (true, OmgBotType::Tilt(4)),
+        macondo::bot_request::BotCode::Level1Probabilistic => (false, OmgBotType::Tilt(1)),
+        macondo::bot_request::BotCode::Level2Probabilistic => (false, OmgBotType::Tilt(2)),
+        macondo::bot_request::BotCode::Level3Probabilistic => (false, OmgBotType::Tilt(3)),
+        macondo::bot_request::BotCode::Level4Probabilistic => (false, OmgBotType::Tilt(4)),
+        macondo::bot_request::BotCode::Level5Probabilistic => (false, OmgBotType::Tilt(5)),
+        macondo::bot_request::BotCode::NoLeaveBot => (false, OmgBotType::Unfiltered),
+        macondo::bot_request::BotCode::SimmingBot => (false, OmgBotType::Sim),
+        macondo::bot_request::BotCode::HastyPlusEndgameBot => (false, OmgBotType::Unfiltered), // not supported
+        macondo::bot_request::BotCode::SimmingInferBot => (false, OmgBotType::Unfiltered), // not supported
+        macondo::bot_request::BotCode::FastMlBot => (false, OmgBotType::Unfiltered), // not supported
+        macondo::bot_request::BotCode::RandomBotWithTemperature => (false, OmgBotType::Unfiltered), // not supported
+        macondo::bot_request::BotCode::SimmingWithMlEvalBot => (false, OmgBotType::Unfiltered), // not supported
+        macondo::bot_request::BotCode::SimmingBotNoEg => (false, OmgBotType::Unfiltered), // not supported
+        macondo::bot_request::BotCode::SimmingInferBotNoEg => (false, OmgBotType::Unfiltered), // not supported
+        macondo::bot_request::BotCode::CustomBot => (false, OmgBotType::Unfiltered), // not supported
+        macondo::bot_request::BotCode::Unknown => (false, OmgBotType::Unfiltered), // not supported
+    }
+}
+
+// What elucubrate decided to play, and how the reply may be timed.
+struct Elucubration {
+    game_event: macondo::GameEvent,
+    move_complexity: delay::MoveComplexity,
+    option_time_budget: Option<TimeBudget>,
+}
+
+// Ok(Err(reason)) when the bot does not respond.
+fn elucubrate<N: kwg::Node + Send + Sync>(
+    ElucubrateArguments {
+        bot_req,
+        tilter,
+        mut game_state,
+        mut tile_placer,
+        kwg,
+        game_config,
+        klv,
+        noleave_klv,
+        mut move_generator,
+        is_jumbled,
+        rack_reader,
+        option_common_word_kwg,
+        option_opening_book,
+        pre_endgame_max_bag,
+        rng,
+    }: ElucubrateArguments<'_, N>,
+) -> Result<Result<Elucubration, &'static str>, Box<dyn std::error::Error>> {
+    let game_history = bot_req.game_history.as_ref().unwrap();
+
+    let reconstruct::RebuiltPosition {
+        reconstruction,
+        is_valid,
+        is_common,
+    } = reconstruct::rebuild_position(
+        game_history,
+        &mut game_state,
+        &mut tile_placer,
+        kwg,
+        option_common_word_kwg.as_deref(),
+        game_config,
+        is_jumbled,
+        rack_reader,
+        rng,
+    )?;
+    let player_indexes = &reconstruction.player_indexes;
+    let num_players = game_history.players.len();
+    let previous_turn = (player_indexes.on_turn + num_players - 1) % num_players;
+    let pass_or_challenge =
+        game_state.bag.is_empty() && game_state.players[previous_turn].rack.is_empty();
+    // roughly how many more moves this player makes. everyone draws from the
+    // same bag, so the more players the fewer moves each.
+    let num_moves_left = (game_state.bag.0.len() + game_config.rack_size() as usize) as i32
+        / (4 * num_players as i32)
+        + 1;
+    let option_time_budget = time_budget(
+        game_history,
+        player_indexes,
+        game_state.turn as usize,
+        num_moves_left,
+    );
+    if let Some(time_budget) = &option_time_budget {
+        println!(
+            "clock: {}ms remaining, {}ms increment, {}ms for this move",
+            time_budget.millis_remaining, time_budget.millis_increment, time_budget.millis_for_move
+        );
+    }
+
+    let my_nickname = &game_history.players[game_state.turn as usize].nickname;
+    println!("it is {my_nickname}'s turn");
+    let (use_common_word, effective_bot_type) = omg_bot_type(bot_req.bot_type());
+    // pass_or_challenge here means the last play went out.
+    let option_challenge_penalty =
+        challenge_penalty(game_history.challenge_rule(), pass_or_challenge);
+    let (let_phony_stand, challenge_uncommon_word) = match effective_bot_type {
+        OmgBotType::Tilt(bot_level) => challenge_mistake_probabilities(bot_level),
+        _ => (0.0, 0.0),
+    };
+    if !is_valid {
+        let challenge_penalty =
+            option_challenge_penalty.ok_or("invalid play under void challenge rule")?;
+        println!("last play is invalid, a wrong challenge would cost {challenge_penalty}");
+        if makes_mistake(rng, let_phony_stand) {
+            println!("letting the phony stand");
+        } else {
+            let mut game_event = macondo::GameEvent::default();
+            game_event.set_type(macondo::game_event::Type::Challenge);
+            return Ok(Ok(Elucubration {
+                game_event,
+                move_complexity: delay::MoveComplexity::challenge(),
+                option_time_budget,
+            }));
+        }
+    } else if !is_common && let Some(challenge_penalty) = option_challenge_penalty {
+        // words outside the common-word lexicon may look like phonies.
+        let cheapness = match challenge_penalty {
+            ChallengePenalty::Nothing => 1.0,
+            ChallengePenalty::OpponentBonus(bonus) => 5.0 / (5 + bonus) as f64,
+            ChallengePenalty::LoseTurn | ChallengePenalty::LoseGame => 0.0,
+        };
+        if makes_mistake(rng, challenge_uncommon_word * cheapness) {
+            println!(
+                "challenging an uncommon word, a wrong challenge would cost {challenge_penalty}"
+            );
+            let mut game_event = macondo::GameEvent::default();
+            game_event.set_type(macondo::game_event::Type::Challenge);
+            return Ok(Ok(Elucubration {
+                game_event,
+                move_complexity: delay::MoveComplexity::challenge(),
+                option_time_budget,
+            }));
+        }
+    }
+    let (mut move_filter, is_simming) = match effective_bot_type {
+        OmgBotType::Tilt(bot_level) if tilter.is_some() && !is_jumbled => (
+            move_filter::GenMoves::Tilt {
+                tilt: tilter.unwrap(),
+                bot_level,
+            },
+            false,
+        ),
+        OmgBotType::Unfiltered => (move_filter::GenMoves::Unfiltered, false),
+        OmgBotType::Sim
+            if !is_jumbled
+                && option_time_budget.as_ref().is_none_or(|time_budget| {
+                    time_budget.millis_for_move >= SIM_MIN_MILLIS_FOR_MOVE
+                }) =>
+        {
+            (move_filter::GenMoves::Unfiltered, true)
+        }
+        OmgBotType::Sim if !is_jumbled => {
+            println!("not enough time to sim");
+            (move_filter::GenMoves::Unfiltered, false)
+        }
+        _ => {
+            return Ok(Err("unsupported combination"));
+        }
+    };
+    let used_kwg = if use_common_word {
+        if option_common_word_kwg.is_none() {
+            return Ok(Err("common_word unavailable"));
+        }
+        option_common_word_kwg.as_ref().unwrap()
+    } else {
+        kwg
+    };
+
+    let alphabet = game_config.alphabet();
+    let board_layout = game_config.board_layout();
+    display::print_board(alphabet, board_layout, &game_state.board_tiles);
+    println!(
+        "{}",
+        alphabet.fmt_rack(&game_state.players[game_state.turn as usize].rack)
+    );
+
+    if let move_filter::GenMoves::Tilt {
+        ref mut tilt,
+        bot_level,
+    } = move_filter
+    {
+        tilt.tilt_by_rng(rng, bot_level);
+        println!(
+            "Effective tilt: tilt factor = {}, leave scale = {}",
+            tilt.tilt_factor, tilt.leave_scale
+        );
+    }
+
+    let board_snapshot = &movegen::BoardSnapshot {
+        board_tiles: &game_state.board_tiles,
+        game_config,
+        kwg: used_kwg,
+        klv: match bot_req.bot_type() {
+            macondo::bot_request::BotCode::NoLeaveBot => noleave_klv,
+            _ => klv,
+        },
+    };
+
+    let rack_size = game_config.rack_size() as usize;
+    // the book is faster than simming, so simming bots look there first.
+    // moves are only generated for a rack in the book, to find the book play.
+    let option_book_play = option_opening_book
+        .as_deref()
+        .filter(|_| {
+            !pass_or_challenge
+                && matches!(effective_bot_type, OmgBotType::Sim)
+                && game_state.board_tiles.iter().all(|&tile| tile == 0)
+        })
+        .and_then(|opening_book| {
+            opening_book.get(&game_history.variant, &game_state.current_player().rack)
+        });
+    let is_book_move = option_book_play.is_some_and(|book_play| {
+        let can_exchange = sim::num_tiles_in_bag(&game_state, rack_size) >= rack_size;
+        move_generator.gen_moves_filtered(
+            &movegen::GenMovesParams {
+                board_snapshot,
+                rack: &game_state.current_player().rack,
+                max_gen: 1_000_000,
+                num_exchanges_by_this_player: 0,
+                always_include_pass: true,
+                dynamic_leaves: None,
+            },
+            |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+            |leave_value: i32| leave_value,
+            |_equity: equity::Equity, play: &movegen::Play| can_exchange || !sim::is_exchange(play),
+        );
+        match book::find(book_play, &move_generator.plays) {
+            Some(book_idx) => {
+                move_generator.plays.swap(0, book_idx);
+                true
+            }
+            None => false,
+        }
+    });
+    // stop simming when the time for the move is up. a pre-endgame that takes
+    // more than half of it leaves the rest for simming.
+    let now = std::time::Instant::now();
+    let option_deadline = option_time_budget.as_ref().map(|time_budget| {
+        now + std::time::Duration::from_millis(time_budget.millis_for_move as u64)
+    });
+    let option_pre_endgame_deadline = option_time_budget.as_ref().map(|time_budget| {
+        now + std::time::Duration::from_millis(time_budget.millis_for_move as u64 / 2)
+    });
+    if is_book_move {
+        println!("playing from the opening book");
+    } else if is_simming
+        && !pass_or_challenge
+        && num_players == 2
+        && (1..=pre_endgame_max_bag).contains(&sim::num_tiles_in_bag(&game_state, rack_size))
+        // the pre-endgame splits the unseen tiles with only one opponent.
+        && pre_endgame::pick_a_move(
+            &game_state,
+            game_config,
+            board_snapshot.kwg,
+            board_snapshot.klv,
+            &mut move_generator,
+            option_pre_endgame_deadline,
+            rng,
+        )?
+    {
+        println!("picked by the pre-endgame");
+    } else if is_simming
+        && !pass_or_challenge
+        && sim_until(
+            game_config,
+            used_kwg,
+            klv,
+            &game_state,
+            &mut move_generator,
+            option_deadline,
+        )
+    {
+        println!("picked by simming");
+    } else {
+        move_picker::MovePicker::Hasty.pick_a_move(
+            &mut move_filter,
+            &mut move_generator,
+            board_snapshot,
+            &game_state,
+            if pass_or_challenge {
+                &[]
+            } else {
+                &game_state.current_player().rack
+            },
+        );
+    }
+    let play = &move_generator.plays[0].play; // assume at least there's always Pass
+    println!("Playing: {}", play.fmt(board_snapshot));
+    let move_kind = match play {
+        movegen::Play::Exchange { tiles } if tiles.is_empty() => delay::MoveKind::Pass,
+        movegen::Play::Exchange { .. } => delay::MoveKind::Exchange,
+        movegen::Play::Place { word, .. }
+            if word.iter().filter(|&&t| t != 0).count() == rack_size =>
+        {
+            delay::MoveKind::Bingo
+        }
+        movegen::Play::Place { .. } => delay::MoveKind::Place,
+    };
+    let game_event = encode::play_to_event(
+        play,
+        game_config,
+        &game_state.board_tiles,
+        &game_state.players[game_state.turn as usize].rack,
+    );
+
+    // with an empty rack, the pass just picked was the only move.
+    if !pass_or_challenge {
+        let can_exchange = sim::num_tiles_in_bag(&game_state, rack_size) >= rack_size;
+        move_generator.gen_moves_filtered(
+            &movegen::GenMovesParams {
+                board_snapshot,
+                rack: &game_state.current_player().rack,
+                max_gen: delay::MAX_ALTERNATIVES + 1,
+                num_exchanges_by_this_player: 0,
+                always_include_pass: true,
+                dynamic_leaves: None,
+            },
+            |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+            |leave_value: i32| leave_value,
+            |_equity: equity::Equity, play: &movegen::Play| can_exchange || !sim::is_exchange(play),
+        );
+    }
+    let move_complexity = delay::MoveComplexity::of_plays(move_kind, &move_generator.plays);
+    Ok(Ok(Elucubration {
+        game_event,
+        move_complexity,
+        option_time_budget,
+    }))
+}
+
+struct EvaluateArguments<'a, N: kwg::Node + Send> {
+    bot_req: Box<macondo::BotRequest>,
+    game_state: game_state::GameState,
+    tile_placer: position::TilePlacer,
+    kwg: &'a std::sync::Arc<kwg::Kwg<N>>,
+    game_config: &'a std::sync::Arc<game_config::GameConfig>,
+    klv: &'a std::sync::Arc<klv::Klv<kwg::Node22>>,
+    move_generator: movegen::KurniaMoveGenerator,
+    is_jumbled: bool,
+    rack_reader: &'a std::sync::Arc<alphabet::AlphabetReader>,
+    play_reader: &'a std::sync::Arc<alphabet::AlphabetReader>,
+}
+
+// number of alternatives listed per turn in a game report
+const REPORT_NUM_TOP_PLAYS: usize = 5;
+
+// One evaluated move, as found by evaluate_each.
+struct EvaluatedMove {
+    event_index: usize,
+    player_index: usize,
+    single_evaluation: macondo::SingleEvaluation,
+    // 0-based index of the played move in the ranked list, if it is there.
+    played_rank: Option<usize>,
+    // the best few plays, as (formatted play, score, equity).
+    top_plays: Vec<(String, i32, equity::Equity)>,
+}
+
+// Evaluate every move the requested user made, mirroring macondo's
+// EvaluationRequest handling: for each of the user's plays, rewind to the
+// position just before it, generate all moves (best equity first), locate the
+// played move, and report equity loss and bingo / star-play flags.
+async fn evaluate<N: kwg::Node + Send + Sync>(
+    evaluate_arguments: EvaluateArguments<'_, N>,
+) -> Result<macondo::Evaluation, Box<dyn std::error::Error>> {
+    let mut play_eval = Vec::new();
+    evaluate_each(evaluate_arguments, 0, |evaluated_move| {
+        play_eval.push(evaluated_move.single_evaluation);
+        Ok(())
+    })?;
+    Ok(macondo::Evaluation { play_eval })
+}
+
+// The players being evaluated: those with the user id, if any, else the one
+// with the nickname.
+fn evaluated_players(
+    game_history: &macondo::GameHistory,
+    user: &str,
+) -> Result<Vec<bool>, Box<dyn std::error::Error>> {
+    let by_user_id = game_history
+        .players
+        .iter()
+        .map(|player| !player.user_id.is_empty() && player.user_id == user)
+        .collect::<Vec<_>>();
+    if by_user_id.contains(&true) {
+        return Ok(by_user_id);
+    }
+    let by_nickname = game_history
+        .players
+        .iter()
+        .map(|player| player.nickname.eq_ignore_ascii_case(user))
+        .collect::<Vec<_>>();
+    if by_nickname
+        .iter()
+        .filter(|&&is_evaluated| is_evaluated)
+        .count()
+        > 1
+    {
+        wolges::return_error!(format!("more than one player is {user:?}"));
+    }
+    Ok(by_nickname)
+}
+
+// Does the work of evaluate, calling f once per evaluated move. Without an
+// evaluation_request, every player's moves are evaluated. The first
+// num_top_plays ranked plays are formatted into EvaluatedMove::top_plays.
+fn evaluate_each<
+    N: kwg::Node + Send + Sync,
+    F: FnMut(EvaluatedMove) -> Result<(), Box<dyn std::error::Error>>,
+>(
+    EvaluateArguments {
+        bot_req,
+        mut game_state,
+        mut tile_placer,
+        kwg,
+        game_config,
+        klv,
+        mut move_generator,
+        is_jumbled,
+        rack_reader,
+        play_reader,
+    }: EvaluateArguments<'_, N>,
+    num_top_plays: usize,
+    mut f: F,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let kwg: &kwg::Kwg<N> = kwg;
+    let klv: &klv::Klv<kwg::Node22> = klv;
+    let game_config: &game_config::GameConfig = game_config;
+    let rack_reader: &alphabet::AlphabetReader = rack_reader;
+    let play_reader: &alphabet::AlphabetReader = play_reader;
+
+    let game_history = bot_req.game_history.as_ref().unwrap();
+    let reconstruction = reconstruct::reconstruct(game_history)?;
+    let player_indexes = &reconstruction.player_indexes;
+    let option_evaluated_players = match &bot_req.evaluation_request {
+        Some(evaluation_request) => {
+            Some(evaluated_players(game_history, &evaluation_request.user)?)
+        }
+        None => None,
+    };
+    let rack_size = game_config.rack_size() as usize;
+
+    game_state.reset();
+
+    let mut rack = Vec::new();
+    let mut word_buf = Vec::new();
+    let mut exch_buf = Vec::new();
+    let mut alpha_buf = Vec::new();
+    let mut seen_moves = fash::MyHashSet::default();
+
+    // Rebuild the board incrementally. Each move is evaluated on the board
+    // before it, which has every earlier placement that was not taken back.
+    let mut placements = reconstruction.placements.iter().peekable();
+    for (i, event) in game_history.events.iter().enumerate() {
+        while let Some(&placement) = placements.next_if(|&&placement| placement < i) {
+            tile_placer.place(
+                &mut game_state.board_tiles,
+                &position::Placement::of_event(&game_history.events[placement])?,
+            )?;
+        }
+
+        let is_move = matches!(
+            event.r#type(),
+            macondo::game_event::Type::TilePlacementMove | macondo::game_event::Type::Exchange
+        );
+        let player_idx = player_indexes.of_event[i];
+        if is_move
+            && option_evaluated_players
+                .as_ref()
+                .is_none_or(|evaluated_players| evaluated_players[player_idx])
+        {
+            parse_rack(rack_reader, &event.rack, &mut rack)?;
+            let board_snapshot = &movegen::BoardSnapshot {
+                board_tiles: &game_state.board_tiles,
+                game_config,
+                kwg,
+                klv,
+            };
+            seen_moves.clear();
+            move_generator.gen_moves_filtered(
+                &movegen::GenMovesParams {
+                    board_snapshot,
+                    rack: &rack,
+                    max_gen: 1_000_000,
+                    num_exchanges_by_this_player: 0,
+                    always_include_pass: false,
+                    dynamic_leaves: None,
+                },
+                |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+                |leave_value: i32| leave_value,
+                |equity: equity::Equity, play: &movegen::Play| {
+                    if !is_jumbled {
+                        return true;
+                    }
+                    // Jumbled words are anagrams; dedupe by sorted tiles so the
+                    // ranked list has one entry per distinct play (matches awsm).
+                    match play {
+                        movegen::Play::Exchange { .. } => true,
+                        movegen::Play::Place {
+                            down,
+                            lane,
+                            idx,
+                            word,
+                            score,
+                        } => {
+                            alpha_buf.clear();
+                            alpha_buf.extend_from_slice(word);
+                            alpha_buf.sort_unstable();
+                            seen_moves.insert((
+                                equity.raw(),
+                                movegen::Play::Place {
+                                    down: *down,
+                                    lane: *lane,
+                                    idx: *idx,
+                                    word: alpha_buf[..].into(),
+                                    score: *score,
+                                },
+                            ))
+                        }
+                    }
+                },
+            );
+
+            let (single_evaluation, played_rank) = eval_played_move(
+                event,
+                &move_generator.plays,
+                rack_size,
+                play_reader,
+                rack_reader,
+                &mut word_buf,
+                &mut exch_buf,
+                &mut alpha_buf,
+            )?;
+            let top_plays = move_generator
+                .plays
+                .iter()
+                .take(num_top_plays)
+                .map(|vm| {
+                    (
+                        format!("{}", vm.play.fmt(board_snapshot)),
+                        match &vm.play {
+                            movegen::Play::Exchange { .. } => 0,
+                            movegen::Play::Place { score, .. } => equity::descale_score(*score),
+                        },
+                        vm.equity,
+                    )
+                })
+                .collect();
+            f(EvaluatedMove {
+                event_index: i,
+                player_index: player_idx,
+                single_evaluation,
+                played_rank,
+                top_plays,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+// Given the ranked plays for the position before `event`, compute the five
+// per-move metrics macondo reports, and the index of the played move in the
+// ranked list. `equity_loss` is the played move's equity minus the best move's
+// equity (<= 0; a phony that never appears in the ranked list scores as 0, so
+// its loss is -top). Star play = the best move beats the second best by more
+// than 10 equity.
+fn eval_played_move(
+    event: &macondo::GameEvent,
+    plays: &[movegen::ValuedMove],
+    rack_size: usize,
+    play_reader: &alphabet::AlphabetReader,
+    rack_reader: &alphabet::AlphabetReader,
+    word_buf: &mut Vec<u8>,
+    exch_buf: &mut Vec<u8>,
+    alpha_buf: &mut Vec<u8>,
+) -> Result<(macondo::SingleEvaluation, Option<usize>), Box<dyn std::error::Error>> {
+    if plays.is_empty() {
+        return Ok((macondo::SingleEvaluation::default(), None));
+    }
+    let placed_count = |word: &[u8]| word.iter().filter(|&&t| t != 0).count();
+
+    let top_equity = plays[0].equity;
+    let top_is_bingo = matches!(
+        &plays[0].play,
+        movegen::Play::Place { word, .. } if placed_count(word) == rack_size
+    );
+
+    let (found_equity, found_idx, played_is_bingo) = match event.r#type() {
+        macondo::game_event::Type::TilePlacementMove => {
+            parse_played_tiles(play_reader, &event.played_tiles, word_buf)?;
+            let (down, lane, idx) = match event.direction() {
+                macondo::game_event::Direction::Vertical => {
+                    (true, event.column as i8, event.row as i8)
+                }
+                macondo::game_event::Direction::Horizontal => {
+                    (false, event.row as i8, event.column as i8)
+                }
+            };
+            let mut found = (equity::Equity::ZERO, None);
+            for (ix, vm) in plays.iter().enumerate() {
+                if let movegen::Play::Place {
+                    down: d,
+                    lane: l,
+                    idx: x,
+                    word: w,
+                    score: s,
+                } = &vm.play
+                {
+                    // Play scores are premultiplied by equity::SCALE (millipoints);
+                    // the history event score is in whole points.
+                    if *d == down
+                        && *l == lane
+                        && *x == idx
+                        && *s == event.score * equity::SCALE
+                        && w[..] == word_buf[..]
+                    {
+                        found = (vm.equity, Some(ix));
+                        break;
+                    }
+                }
+            }
+            (found.0, found.1, placed_count(word_buf) == rack_size)
+        }
+        macondo::game_event::Type::Exchange => {
+            parse_rack(rack_reader, &event.exchanged, exch_buf)?;
+            exch_buf.sort_unstable();
+            let mut found = (equity::Equity::ZERO, None);
+            for (ix, vm) in plays.iter().enumerate() {
+                if let movegen::Play::Exchange { tiles } = &vm.play {
+                    alpha_buf.clear();
+                    alpha_buf.extend_from_slice(tiles);
+                    alpha_buf.sort_unstable();
+                    if alpha_buf[..] == exch_buf[..] {
+                        found = (vm.equity, Some(ix));
+                        break;
+                    }
+                }
+            }
+            (found.0, found.1, false)
+        }
+        _ => (equity::Equity::ZERO, None, false),
+    };
+
+    let possible_star_play =
+        plays.len() > 1 && top_equity.raw() - plays[1].equity.raw() > 10 * equity::SCALE;
+    Ok((
+        macondo::SingleEvaluation {
+            equity_loss: found_equity.as_f64() - top_equity.as_f64(),
+            win_pct_loss: 0.0,
+            missed_bingo: top_is_bingo && !played_is_bingo,
+            possible_star_play,
+            missed_star_play: possible_star_play && found_idx.is_some_and(|ix| ix > 0),
+            top_is_bingo,
+        },
+        found_idx,
+    ))
+}
+
+// Builds a report on every evaluated move of a finished game.
+fn make_report<N: kwg::Node + Send + Sync>(
+    bot_req: Box<macondo::BotRequest>,
+    game_history: &macondo::GameHistory,
+    kwg: &std::sync::Arc<kwg::Kwg<N>>,
+    klv: &std::sync::Arc<klv::Klv<kwg::Node22>>,
+    game_config: &std::sync::Arc<game_config::GameConfig>,
+    rack_reader: &std::sync::Arc<alphabet::AlphabetReader>,
+    play_reader: &std::sync::Arc<alphabet::AlphabetReader>,
+) -> Result<report::GameReport, Box<dyn std::error::Error>> {
+    let is_jumbled = match game_config.game_rules() {
+        game_config::GameRules::Classic => false,
+        game_config::GameRules::Jumbled => true,
+    };
+    let mut turns = Vec::new();
+    evaluate_each(
+        EvaluateArguments {
+            bot_req,
+            game_state: game_state::GameState::new(game_config),
+            tile_placer: position::TilePlacer::new(
+                std::sync::Arc::clone(game_config),
+                std::sync::Arc::clone(play_reader),
+            ),
+            kwg,
+            game_config,
+            klv,
+            move_generator: movegen::KurniaMoveGenerator::new(game_config),
+            is_jumbled,
+            rack_reader,
+            play_reader,
+        },
+        REPORT_NUM_TOP_PLAYS,
+        |EvaluatedMove {
+             event_index,
+             player_index,
+             single_evaluation,
+             played_rank,
+             top_plays,
+         }| {
+            let event = &game_history.events[event_index];
+            turns.push(report::TurnReport {
+                event_index,
+                nickname: game_history.players[player_index].nickname.clone(),
+                rack: event.rack.clone(),
+                played: report::fmt_played(event),
+                played_rank: played_rank.map(|ix| ix + 1),
+                equity_loss: single_evaluation.equity_loss,
+                missed_bingo: single_evaluation.missed_bingo,
+                possible_star_play: single_evaluation.possible_star_play,
+                missed_star_play: single_evaluation.missed_star_play,
+                top_is_bingo: single_evaluation.top_is_bingo,
+                alternatives: top_plays
+                    .into_iter()
+                    .map(|(play, score, equity)| report::Alternative {
+                        play,
+                        score,
+                        equity: equity.as_f64(),
+                    })
+                    .collect(),
+            });
+            Ok(())
+        },
+    )?;
+    Ok(report::GameReport {
+        uid: game_history.uid.clone(),
+        lexicon: game_history.lexicon.clone(),
+        variant: game_history.variant.clone(),
+        players: game_history
+            .players
+            .iter()
+            .map(|player| player.nickname.clone())
+            .collect(),
+        turns,
+    })
+}
+
+// args: game_history_file output_prefix [user]
+// The file holds an encoded GameHistory. Writes output_prefix.gcg and
+// output_prefix.json, covering only the user's moves if a user is given.
+pub fn do_report(lexicons: &Lexicons, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 && args.len() != 3 {
+        wolges::return_error!("args: report game_history_file output_prefix [user]".into());
+    }
+    let game_history = macondo::GameHistory::decode(&*std::fs::read(&args[0])?)?;
+    let RecycledStuffs {
+        bot_req,
+        kwg,
+        klv,
+        game_config,
+        rack_reader,
+        play_reader,
+        ..
+    } = lexicons.recycle(Box::new(macondo::BotRequest {
+        game_history: Some(game_history.clone()),
+        evaluation_request: args
+            .get(2)
+            .map(|user| macondo::EvaluationRequest { user: user.clone() }),
+        ..Default::default()
+    }))?;
+    let game_report = match *kwg {
+        ArcKwgEither::Node22(ref kwg) => make_report(
+            bot_req,
+            &game_history,
+            kwg,
+            &klv,
+            &game_config,
+            &rack_reader,
+            &play_reader,
+        ),
+        ArcKwgEither::Node24(ref kwg) => make_report(
+            bot_req,
+            &game_history,
+            kwg,
+            &klv,
+            &game_config,
+            &rack_reader,
+            &play_reader,
+        ),
+    }?;
+    let output_prefix = &args[1];
+    std::fs::write(
+        format!("{output_prefix}.gcg"),
+        report::to_gcg(
+            &game_history,
+            &reconstruct::determine_player_indexes(&game_history)?.of_event,
+            &game_report,
+        )?,
+    )?;
+    std::fs::write(
+        format!("{output_prefix}.json"),
+        report::to_json(&game_report)?,
+    )?;
+    println!("wrote {output_prefix}.gcg and {output_prefix}.json");
+    Ok(())
+}
+
+enum Language {
+    Catalan,
+    English,
+    French,
+    German,
+    Norwegian,
+    Polish,
+    Spanish,
+}
+
+struct RecycledStuffs {
+    bot_req: Box<macondo::BotRequest>,
+    kwg: std::sync::Arc<ArcKwgEither>,
+    klv: std::sync::Arc<klv::Klv<kwg::Node22>>,
+    game_config: std::sync::Arc<game_config::GameConfig>,
+    tilter: Option<move_filter::Tilt<'static>>,
+    rack_reader: std::sync::Arc<alphabet::AlphabetReader>,
+    play_reader: std::sync::Arc<alphabet::AlphabetReader>,
+    option_common_word_kwg: Option<std::sync::Arc<ArcKwgEither>>,
+    option_opening_book: Option<std::sync::Arc<book::OpeningBook>>,
+    evaluation_cache: std::sync::Arc<std::sync::Mutex<eval_cache::EvaluationCache>>,
+}
+
+// everything loaded at startup, by lexicon
+pub struct Lexicons {
+    noleave_klv: std::sync::Arc<klv::Klv<kwg::Node22>>,
+    game_configs: std::collections::HashMap<String, std::sync::Arc<game_config::GameConfig>>,
+    jumbled_game_configs:
+        std::collections::HashMap<String, std::sync::Arc<game_config::GameConfig>>,
+    super_game_configs: std::collections::HashMap<String, std::sync::Arc<game_config::GameConfig>>,
+    jumbled_super_game_configs:
+        std::collections::HashMap<String, std::sync::Arc<game_config::GameConfig>>,
+    klvs: std::collections::HashMap<String, std::sync::Arc<klv::Klv<kwg::Node22>>>,
+    super_klvs: std::collections::HashMap<String, std::sync::Arc<klv::Klv<kwg::Node22>>>,
+    kwgs: std::collections::HashMap<String, std::sync::Arc<ArcKwgEither>>,
+    tilters: std::collections::HashMap<String, move_filter::Tilt<'static>>,
+    kads: std::collections::HashMap<String, std::sync::Arc<ArcKwgEither>>,
+    rack_readers: std::collections::HashMap<String, std::sync::Arc<alphabet::AlphabetReader>>,
+    play_readers: std::collections::HashMap<String, std::sync::Arc<alphabet::AlphabetReader>>,
+    common_word_kwgs: std::collections::HashMap<String, std::sync::Arc<ArcKwgEither>>,
+    opening_books: std::collections::HashMap<String, std::sync::Arc<book::OpeningBook>>,
+    evaluation_cache: std::sync::Arc<std::sync::Mutex<eval_cache::EvaluationCache>>,
+}
+
+impl Lexicons {
+    // picks the resources for the request's lexicon and variant
+    fn recycle(
+        &self,
+        bot_req: Box<macondo::BotRequest>,
+    ) -> Result<RecycledStuffs, Box<dyn std::error::Error>> {
+        let game_history = bot_req.game_history.as_ref().ok_or("need a game history")?;
+        if game_history.players.len() < 2 {
+            wolges::return_error!("needs at least two players".into());
+        }
+
+        let (is_jumbled, is_super) = match &*game_history.variant {
+            "wordsmog" => (true, false),
+            "classic_super" => (false, true),
+            "wordsmog_super" => (true, true),
+            _ => (false, false),
+        };
+        // todo: transpose these?
+        let game_config = match (is_jumbled, is_super) {
+            (true, false) => &self.jumbled_game_configs,
+            (false, true) => &self.super_game_configs,
+            (true, true) => &self.jumbled_super_game_configs,
+            (false, false) => &self.game_configs,
+        }
+        .get(&game_history.lexicon)
+        .ok_or("not familiar with the lexicon")?;
+        let klv = if is_super {
+            self.super_klvs.get(&game_history.lexicon)
+        } else {
+            self.klvs.get(&game_history.lexicon)
+        }
+        .ok_or("not familiar with the lexicon")?;
+        let (kwg, tilter) = match is_jumbled {
+            true => {
+                let kad = self
+                    .kads
+                    .get(&game_history.lexicon)
+                    .ok_or("not familiar with the lexicon")?;
+                (kad, None)
+            }
+            false => {
+                let kwg = self
+                    .kwgs
+                    .get(&game_history.lexicon)
+                    .ok_or("not familiar with the lexicon")?;
+                (kwg, self.tilters.get(&game_history.lexicon))
+            }
+        };
+        let rack_reader = self
+            .rack_readers
+            .get(&game_history.lexicon)
+            .ok_or("not familiar with the lexicon")?;
+        let play_reader = self
+            .play_readers
+            .get(&game_history.lexicon)
+            .ok_or("not familiar with the lexicon")?;
+        let option_common_word_kwg = self.common_word_kwgs.get(&game_history.lexicon);
+        // ensure it has the same node type as kwg
+        let kwg_variant = match **kwg {
+            ArcKwgEither::Node22(_) => 22,
+            ArcKwgEither::Node24(_) => 24,
+        };
+        let common_word_kwg_variant = match option_common_word_kwg {
+            None => kwg_variant,
+            Some(arc_thing) => match **arc_thing {
+                ArcKwgEither::Node22(_) => 22,
+                ArcKwgEither::Node24(_) => 24,
+            },
+        };
+        if kwg_variant != common_word_kwg_variant {
+            wolges::return_error!("common word kwg has different variant".into());
+        }
+
+        Ok(RecycledStuffs {
+            bot_req,
+            kwg: std::sync::Arc::clone(kwg),
+            klv: std::sync::Arc::clone(klv),
+            game_config: std::sync::Arc::clone(game_config),
+            tilter: tilter.cloned(),
+            rack_reader: std::sync::Arc::clone(rack_reader),
+            play_reader: std::sync::Arc::clone(play_reader),
+            option_common_word_kwg: option_common_word_kwg.cloned(),
+            option_opening_book: self.opening_books.get(&game_history.lexicon).cloned(),
+            evaluation_cache: std::sync::Arc::clone(&self.evaluation_cache),
+        })
+    }
+
+    // Reads LEXICON.klv2, super-LEXICON.klv2, LEXICON.kwg (or LEXICON.kbwg),
+    // LEXICON.kad and LEXICON.book in dir for every known lexicon. Missing
+    // files are warned about, and their lexicons are not playable.
+    pub fn load(dir: &std::path::Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let noleave_klv = std::sync::Arc::new(klv::Klv::from_bytes_alloc(klv::EMPTY_KLV_BYTES));
+        // one per supported config
+        let catalan_game_config = std::sync::Arc::new(game_config::make_catalan_game_config());
+        let jumbled_catalan_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_catalan_game_config());
+        let super_catalan_game_config =
+            std::sync::Arc::new(game_config::make_super_catalan_game_config());
+        let jumbled_super_catalan_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_super_catalan_game_config());
+        let english_game_config = std::sync::Arc::new(game_config::make_english_game_config());
+        let jumbled_english_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_english_game_config());
+        let super_english_game_config =
+            std::sync::Arc::new(game_config::make_super_english_game_config());
+        let jumbled_super_english_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_super_english_game_config());
+        let french_game_config = std::sync::Arc::new(game_config::make_french_game_config());
+        let jumbled_french_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_french_game_config());
+        let german_game_config = std::sync::Arc::new(game_config::make_german_game_config());
+        let jumbled_german_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_german_game_config());
+        let norwegian_game_config = std::sync::Arc::new(game_config::make_norwegian_game_config());
+        let jumbled_norwegian_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_norwegian_game_config());
+        let polish_game_config = std::sync::Arc::new(game_config::make_polish_game_config());
+        let jumbled_polish_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_polish_game_config());
+        let spanish_game_config = std::sync::Arc::new(game_config::make_spanish_game_config());
+        let jumbled_spanish_game_config =
+            std::sync::Arc::new(game_config::make_jumbled_spanish_game_config());
+        let catalan_rack_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_racks(
+            catalan_game_config.alphabet(),
+        ));
+        let english_rack_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_racks(
+            english_game_config.alphabet(),
+        ));
+        let french_rack_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_racks(
+            french_game_config.alphabet(),
+        ));
+        let german_rack_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_racks(
+            german_game_config.alphabet(),
+        ));
+        let norwegian_rack_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_racks(
+            norwegian_game_config.alphabet(),
+        ));
+        let polish_rack_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_racks(
+            polish_game_config.alphabet(),
+        ));
+        let spanish_rack_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_racks(
+            spanish_game_config.alphabet(),
+        ));
+        let catalan_play_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_plays(
+            catalan_game_config.alphabet(),
+        ));
+        let english_play_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_plays(
+            english_game_config.alphabet(),
+        ));
+        let french_play_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_plays(
+            french_game_config.alphabet(),
+        ));
+        let german_play_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_plays(
+            german_game_config.alphabet(),
+        ));
+        let norwegian_play_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_plays(
+            norwegian_game_config.alphabet(),
+        ));
+        let polish_play_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_plays(
+            polish_game_config.alphabet(),
+        ));
+        let spanish_play_reader = std::sync::Arc::new(alphabet::AlphabetReader::new_for_plays(
+            spanish_game_config.alphabet(),
+        ));
+        let lexicons = [
+            ("CGL", Language::German),
+            ("CSW19", Language::English),
+            ("CSW19X", Language::English),
+            ("CSW21", Language::English),
+            ("CSW24", Language::English),
+            ("CSW24X", Language::English),
+            ("DISC2", Language::Catalan),
+            ("ECWL", Language::English),
+            ("FILE2017", Language::Spanish),
+            ("FRA20", Language::French),
+            ("FRA24", Language::French),
+            ("NSF21", Language::Norwegian),
+            ("NSF22", Language::Norwegian),
+            ("NSF23", Language::Norwegian),
+            ("NSF25", Language::Norwegian),
+            ("NSWL20", Language::English),
+            ("NWL18", Language::English),
+            ("NWL20", Language::English),
+            ("NWL23", Language::English),
+            ("OSPS49", Language::Polish),
+            ("OSPS50", Language::Polish),
+            ("RD28", Language::German),
+            ("RD29", Language::German),
+        ];
+        let mut game_configs = std::collections::HashMap::new();
+        let mut jumbled_game_configs = std::collections::HashMap::new();
+        let mut super_game_configs = std::collections::HashMap::new();
+        let mut jumbled_super_game_configs = std::collections::HashMap::new();
+        let mut klvs = std::collections::HashMap::new();
+        let mut super_klvs = std::collections::HashMap::new();
+        let mut kwgs = std::collections::HashMap::new();
+        let mut tilters = std::collections::HashMap::new();
+        let mut kads = std::collections::HashMap::new();
+        let mut rack_readers = std::collections::HashMap::new();
+        let mut play_readers = std::collections::HashMap::new();
+        let mut opening_books = std::collections::HashMap::new();
+        for (lexicon, language) in lexicons.iter() {
+            game_configs.insert(
+                lexicon.to_string(),
+                match language {
+                    Language::Catalan => catalan_game_config.clone(),
+                    Language::English => english_game_config.clone(),
+                    Language::French => french_game_config.clone(),
+                    Language::German => german_game_config.clone(),
+                    Language::Norwegian => norwegian_game_config.clone(),
+                    Language::Polish => polish_game_config.clone(),
+                    Language::Spanish => spanish_game_config.clone(),
+                },
+            );
+            jumbled_game_configs.insert(
+                lexicon.to_string(),
+                match language {
+                    Language::Catalan => jumbled_catalan_game_config.clone(),
+                    Language::English => jumbled_english_game_config.clone(),
+                    Language::French => jumbled_french_game_config.clone(),
+                    Language::German => jumbled_german_game_config.clone(),
+                    Language::Norwegian => jumbled_norwegian_game_config.clone(),
+                    Language::Polish => jumbled_polish_game_config.clone(),
+                    Language::Spanish => jumbled_spanish_game_config.clone(),
+                },
+            );
+            if let Language::Catalan = language {
+                super_game_configs.insert(lexicon.to_string(), super_catalan_game_config.clone());
+                jumbled_super_game_configs.insert(
+                    lexicon.to_string(),
+                    jumbled_super_catalan_game_config.clone(),
+                );
+            }
+            if let Language::English = language {
+                super_game_configs.insert(lexicon.to_string(), super_english_game_config.clone());
+                jumbled_super_game_configs.insert(
+                    lexicon.to_string(),
+                    jumbled_super_english_game_config.clone(),
+                );
+            }
+            match std::fs::read(dir.join(format!("{lexicon}.klv2"))) {
+                Ok(klv_bytes) => {
+                    let klv_arc = std::sync::Arc::new(klv::Klv::from_bytes_alloc(&klv_bytes));
+                    match std::fs::read(dir.join(format!("super-{lexicon}.klv2"))) {
+                        Ok(super_klv_bytes) => {
+                            let super_klv_arc =
+                                std::sync::Arc::new(klv::Klv::from_bytes_alloc(&super_klv_bytes));
+                            super_klvs.insert(lexicon.to_string(), super_klv_arc);
+                        }
+                        Err(_) => {
+                            super_klvs.insert(lexicon.to_string(), std::sync::Arc::clone(&klv_arc));
+                        }
+                    }
+                    klvs.insert(lexicon.to_string(), klv_arc);
+                }
+                Err(err) => {
+                    eprintln!("warning: {lexicon}.klv2: {err}");
+                }
+            }
+            match std::fs::read(dir.join(format!("{lexicon}.kwg"))) {
+                Ok(kwg_bytes) => {
+                    let kwg_arc = ArcKwgEither::Node22(std::sync::Arc::new(
+                        kwg::Kwg::from_bytes_alloc(&kwg_bytes),
+                    ));
+                    tilters.insert(
+                        lexicon.to_string(),
+                        move_filter::Tilt::new(
+                            game_configs.get(*lexicon).unwrap(),
+                            &match kwg_arc {
+                                ArcKwgEither::Node22(ref kwg) => kwg,
+                                _ => panic!(),
+                            }
+                            .clone(),
+                            move_filter::Tilt::length_importances(),
+                        ),
+                    );
+                    kwgs.insert(lexicon.to_string(), std::sync::Arc::new(kwg_arc));
+                }
+                Err(err_kwg) => match std::fs::read(dir.join(format!("{lexicon}.kbwg"))) {
+                    Ok(kbwg_bytes) => {
+                        let kbwg_arc = ArcKwgEither::Node24(std::sync::Arc::new(
+                            kwg::Kwg::from_bytes_alloc(&kbwg_bytes),
+                        ));
+                        tilters.insert(
+                            lexicon.to_string(),
+                            move_filter::Tilt::new(
+                                game_configs.get(*lexicon).unwrap(),
+                                &match kbwg_arc {
+                                    ArcKwgEither::Node24(ref kbwg) => kbwg,
+                                    _ => panic!(),
+                                }
+                                .clone(),
+                                move_filter::Tilt::length_importances(),
+                            ),
+                        );
+                        kwgs.insert(lexicon.to_string(), std::sync::Arc::new(kbwg_arc));
+                    }
+                    Err(err_kbwg) => {
+                        eprintln!("warning: {lexicon}.kwg: {err_kwg}");
+                        eprintln!("warning: {lexicon}.kbwg: {err_kbwg}");
+                    }
+                },
+            }
+            match std::fs::read(dir.join(format!("{lexicon}.kad"))) {
+                Ok(kad_bytes) => {
+                    let kad_arc = ArcKwgEither::Node22(std::sync::Arc::new(
+                        kwg::Kwg::from_bytes_alloc(&kad_bytes),
+                    ));
+                    kads.insert(lexicon.to_string(), std::sync::Arc::new(kad_arc));
+                }
+                Err(err) => {
+                    eprintln!("warning: {lexicon}.kad: {err}");
+                }
+            }
+            rack_readers.insert(
+                lexicon.to_string(),
+                match language {
+                    Language::Catalan => catalan_rack_reader.clone(),
+                    Language::English => english_rack_reader.clone(),
+                    Language::French => french_rack_reader.clone(),
+                    Language::German => german_rack_reader.clone(),
+                    Language::Norwegian => norwegian_rack_reader.clone(),
+                    Language::Polish => polish_rack_reader.clone(),
+                    Language::Spanish => spanish_rack_reader.clone(),
+                },
+            );
+            play_readers.insert(
+                lexicon.to_string(),
+                match language {
+                    Language::Catalan => catalan_play_reader.clone(),
+                    Language::English => english_play_reader.clone(),
+                    Language::French => french_play_reader.clone(),
+                    Language::German => german_play_reader.clone(),
+                    Language::Norwegian => norwegian_play_reader.clone(),
+                    Language::Polish => polish_play_reader.clone(),
+                    Language::Spanish => spanish_play_reader.clone(),
+                },
+            );
+            // books are optional, so a missing one is not worth a warning.
+            if let Ok(book_text) = std::fs::read_to_string(dir.join(format!("{lexicon}.book"))) {
+                let opening_book = book::OpeningBook::parse(
+                    &book_text,
+                    &rack_readers[*lexicon],
+                    &play_readers[*lexicon],
+                    |variant| {
+                        match variant {
+                            "classic" => game_configs.get(*lexicon),
+                            "wordsmog" => jumbled_game_configs.get(*lexicon),
+                            "classic_super" => super_game_configs.get(*lexicon),
+                            "wordsmog_super" => jumbled_super_game_configs.get(*lexicon),
+                            _ => None,
+                        }
+                        .map(|game_config| &**game_config)
+                    },
+                )
+                .map_err(|err| format!("{lexicon}.book: {err}"))?;
+                opening_books.insert(lexicon.to_string(), std::sync::Arc::new(opening_book));
+            }
+        }
+        let mut common_word_kwgs = std::collections::HashMap::new();
+        if let Some(ecwl_kwg) = kwgs.get("ECWL") {
+            let mut v1 = Vec::<bites::Bites>::new();
+            each_word(ecwl_kwg, |w| v1.push(w.into()));
+            let mut v2 = Vec::<bites::Bites>::new();
+            for (lexicon, language) in lexicons.iter() {
+                if *lexicon != "ECWL" && matches!(language, Language::English) {
+                    v2.clear();
+                    let mut v1p = 0;
+                    let kwg = kwgs.get(*lexicon).unwrap();
+                    each_word(kwg, |w| {
+                        while v1p < v1.len() {
+                            match v1[v1p][..].cmp(w) {
+                                std::cmp::Ordering::Greater => break,
+                                std::cmp::Ordering::Less => v1p += 1,
+                                std::cmp::Ordering::Equal => {
+                                    v2.push(w.into());
+                                    v1p += 1;
+                                    break;
+                                }
+                            }
+                        }
+                    });
+                    common_word_kwgs.insert(
+                        lexicon.to_string(),
+                        std::sync::Arc::new(match **kwg {
+                            ArcKwgEither::Node22(_) => ArcKwgEither::Node22(std::sync::Arc::new(
+                                kwg::Kwg::from_bytes_alloc(&build::build(
+                                    build::BuildContent::Gaddawg,
+                                    build::BuildLayout::Wolges,
+                                    &v2,
+                                )?),
+                            )),
+                            ArcKwgEither::Node24(_) => ArcKwgEither::Node24(std::sync::Arc::new(
+                                kwg::Kwg::from_bytes_alloc(&build::build_big(
+                                    build::BuildContent::Gaddawg,
+                                    build::BuildLayout::Wolges,
+                                    &v2,
+                                )?),
+                            )),
+                        }),
+                    );
+                }
+            }
+        }
+        if let Some(cgl_kwg) = kwgs.get("CGL") {
+            let mut v1 = Vec::<bites::Bites>::new();
+            each_word(cgl_kwg, |w| v1.push(w.into()));
+            let mut v2 = Vec::<bites::Bites>::new();
+            for (lexicon, language) in lexicons.iter() {
+                if *lexicon != "CGL" && matches!(language, Language::German) {
+                    v2.clear();
+                    let mut v1p = 0;
+                    let kwg = kwgs.get(*lexicon).unwrap();
+                    each_word(kwg, |w| {
+                        while v1p < v1.len() {
+                            match v1[v1p][..].cmp(w) {
+                                std::cmp::Ordering::Greater => break,
+                                std::cmp::Ordering::Less => v1p += 1,
+                                std::cmp::Ordering::Equal => {
+                                    v2.push(w.into());
+                                    v1p += 1;
+                                    break;
+                                }
+                            }
+                        }
+                    });
+                    common_word_kwgs.insert(
+                        lexicon.to_string(),
+                        std::sync::Arc::new(match **kwg {
+                            ArcKwgEither::Node22(_) => ArcKwgEither::Node22(std::sync::Arc::new(
+                                kwg::Kwg::from_bytes_alloc(&build::build(
+                                    build::BuildContent::Gaddawg,
+                                    build::BuildLayout::Wolges,
+                                    &v2,
+                                )?),
+                            )),
+                            ArcKwgEither::Node24(_) => ArcKwgEither::Node24(std::sync::Arc::new(
+                                kwg::Kwg::from_bytes_alloc(&build::build_big(
+                                    build::BuildContent::Gaddawg,
+                                    build::BuildLayout::Wolges,
+                                    &v2,
+                                )?),
+                            )),
+                        }),
+                    );
+                }
+            }
+        }
+
+        Ok(Self {
+            noleave_klv,
+            game_configs,
+            jumbled_game_configs,
+            super_game_configs,
+            jumbled_super_game_configs,
+            klvs,
+            super_klvs,
+            kwgs,
+            tilters,
+            kads,
+            rack_readers,
+            play_readers,
+            common_word_kwgs,
+            opening_books,
+            evaluation_cache: std::sync::Arc::new(std::sync::Mutex::new(
+                eval_cache::EvaluationCache::from_env()?,
+            )),
+        })
+    }
+}
+
+// The settings that come from the environment.
+pub struct Settings {
+    delay_models: delay::DelayModels,
+    seeder: seed::Seeder,
+    pre_endgame_max_bag: usize,
+}
+
+impl Settings {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            delay_models: delay::DelayModels::from_env()?,
+            seeder: seed::Seeder::from_env()?,
+            pre_endgame_max_bag: pre_endgame::max_bag_tiles_from_env()?,
+        })
+    }
+
+    pub fn master_seed(&self) -> u64 {
+        self.seeder.master_seed()
+    }
+}
+
+// Answers every request from the transport until there are no more.
+pub async fn serve<T: transport::Transport>(
+    transport: &mut T,
+    lexicons: &Lexicons,
+    Settings {
+        delay_models,
+        seeder,
+        pre_endgame_max_bag,
+    }: &Settings,
+) {
+    let noleave_klv = &lexicons.noleave_klv;
+    let pre_endgame_max_bag = *pre_endgame_max_bag;
+    while let Some((bot_req, responder)) = transport.receive().await {
+        let msg_received_instant = std::time::Instant::now();
+        // allocates a clone.
+        let option_game_id = bot_req
+            .as_ref()
+            .ok()
+            .and_then(|bot_req| bot_req.game_history.as_ref())
+            .map(|game_history| game_history.uid.clone());
+        let recycled_stuffs = (|| -> Result<RecycledStuffs, Box<dyn std::error::Error>> {
+            let bot_req = Box::new(bot_req.map_err(|err| -> Box<dyn std::error::Error> { err })?);
+            println!("{bot_req:?}");
+            lexicons.recycle(bot_req)
+        })();
+        match recycled_stuffs {
+            Err(err) if responder.wants_analysis() => {
+                let analysis_resp = analysis::AnalysisResponse {
+                    error: err.to_string(),
+                    game_id: option_game_id.unwrap_or("".to_string()),
+                    ..Default::default()
+                };
+                println!("{analysis_resp:?}");
+                if let Err(err) = responder.send_analysis(analysis_resp).await {
+                    println!("cannot send response: {err}");
+                }
+            }
+            Err(err) => {
+                let bot_resp = macondo::BotResponse {
+                    response: Some(macondo::bot_response::Response::Error(err.to_string())),
+                    game_id: option_game_id.unwrap_or("".to_string()), // does not seem to be used by liwords
+                    ..Default::default()
+                };
+                println!("{bot_resp:?}");
+                if let Err(err) = responder.send(bot_resp).await {
+                    println!("cannot send response: {err}");
+                }
+            }
+            Ok(RecycledStuffs {
+                bot_req,
+                kwg,
+                klv,
+                game_config,
+                tilter,
+                rack_reader,
+                play_reader,
+                option_common_word_kwg,
+                option_opening_book,
+                evaluation_cache,
+            }) => match *kwg {
+                ArcKwgEither::Node22(ref kwg) => do_it(DoItArguments {
+                    noleave_klv,
+                    msg_received_instant,
+                    delay_model: delay_models.get(bot_req.bot_type()),
+                    seeder,
+                    pre_endgame_max_bag,
+                    option_game_id,
+                    responder,
+                    bot_req,
+                    kwg: kwg.clone(),
+                    klv,
+                    game_config,
+                    tilter,
+                    rack_reader,
+                    play_reader,
+                    option_opening_book,
+                    evaluation_cache,
+                    option_common_word_kwg: option_common_word_kwg.and_then(|arc_thing| {
+                        match *arc_thing {
+                            ArcKwgEither::Node22(ref common_word_kwg) => {
+                                Some(common_word_kwg.clone())
+                            }
+                            _ => None,
+                        }
+                    }),
+                }),
+                ArcKwgEither::Node24(ref kwg) => do_it(DoItArguments {
+                    noleave_klv,
+                    msg_received_instant,
+                    delay_model: delay_models.get(bot_req.bot_type()),
+                    seeder,
+                    pre_endgame_max_bag,
+                    option_game_id,
+                    responder,
+                    bot_req,
+                    kwg: kwg.clone(),
+                    klv,
+                    game_config,
+                    tilter,
+                    rack_reader,
+                    play_reader,
+                    option_opening_book,
+                    evaluation_cache,
+                    option_common_word_kwg: option_common_word_kwg.and_then(|arc_thing| {
+                        match *arc_thing {
+                            ArcKwgEither::Node24(ref common_word_kwg) => {
+                                Some(common_word_kwg.clone())
+                            }
+                            _ => None,
+                        }
+                    }),
+                }),
+            },
+        };
+    }
+}
+
+struct DoItArguments<'a, R: transport::Responder, N: kwg::Node + Send + Sync + 'static> {
+    noleave_klv: &'a std::sync::Arc<klv::Klv<kwg::Node22>>,
+    msg_received_instant: std::time::Instant,
+    delay_model: delay::DelayModel,
+    seeder: &'a seed::Seeder,
+    pre_endgame_max_bag: usize,
+    option_game_id: Option<String>,
+    responder: R,
+    bot_req: Box<macondo::BotRequest>,
+    kwg: std::sync::Arc<kwg::Kwg<N>>,
+    klv: std::sync::Arc<klv::Klv<kwg::Node22>>,
+    game_config: std::sync::Arc<game_config::GameConfig>,
+    tilter: Option<move_filter::Tilt<'static>>,
+    rack_reader: std::sync::Arc<alphabet::AlphabetReader>,
+    play_reader: std::sync::Arc<alphabet::AlphabetReader>,
+    option_opening_book: Option<std::sync::Arc<book::OpeningBook>>,
+    evaluation_cache: std::sync::Arc<std::sync::Mutex<eval_cache::EvaluationCache>>,
+    option_common_word_kwg: Option<std::sync::Arc<kwg::Kwg<N>>>,
+}
+
+fn do_it<R: transport::Responder, N: kwg::Node + Send + Sync + 'static>(
+    DoItArguments {
+        noleave_klv,
+        msg_received_instant,
+        delay_model,
+        seeder,
+        pre_endgame_max_bag,
+        option_game_id,
+        responder,
+        bot_req,
+        kwg,
+        klv,
+        game_config,
+        tilter,
+        rack_reader,
+        play_reader,
+        option_opening_book,
+        evaluation_cache,
+        option_common_word_kwg,
+    }: DoItArguments<'_, R, N>,
+) {
+    let noleave_klv = std::sync::Arc::clone(noleave_klv);
+    let seed = seeder.request_seed(
+        option_game_id.as_deref().unwrap_or(""),
+        bot_req
+            .game_history
+            .as_ref()
+            .map_or(0, |game_history| game_history.events.len()),
+    );
+    tokio::spawn(async move {
+        // everything random about the reply is drawn from this.
+        println!("seed: {seed}");
+        let mut rng = rand::rngs::ChaCha20Rng::seed_from_u64(seed);
+        let game_state = game_state::GameState::new(&game_config);
+        let move_generator = movegen::KurniaMoveGenerator::new(&game_config);
+        let mut option_bot_resp = None;
+        let mut option_move_complexity = None;
+        let mut option_time_budget = None;
+        let mut should_reply = true;
+        {
+            let tile_placer = position::TilePlacer::new(
+                std::sync::Arc::clone(&game_config),
+                std::sync::Arc::clone(&play_reader),
+            );
+
+            let is_jumbled = match game_config.game_rules() {
+                game_config::GameRules::Classic => false,
+                game_config::GameRules::Jumbled => true,
+            };
+            if responder.wants_analysis() {
+                // Analyses never sleep. Simming takes a while, so it is kept
+                // off the threads that serve the other requests.
+                let analysis_result = tokio::task::spawn_blocking(move || {
+                    analyze::analyze(analyze::AnalyzeArguments {
+                        bot_req,
+                        game_state,
+                        tile_placer,
+                        kwg: &kwg,
+                        game_config: &game_config,
+                        klv: &klv,
+                        move_generator,
+                        is_jumbled,
+                        rack_reader: &rack_reader,
+                        rng: &mut rng,
+                    })
+                    .map_err(|err| err.to_string())
+                })
+                .await
+                .unwrap_or_else(|err| Err(err.to_string()));
+                let analysis_resp = match analysis_result {
+                    Ok(candidates) => analysis::AnalysisResponse {
+                        candidates,
+                        game_id: option_game_id.unwrap_or("".to_string()),
+                        ..Default::default()
+                    },
+                    Err(err) => analysis::AnalysisResponse {
+                        error: err,
+                        game_id: option_game_id.unwrap_or("".to_string()),
+                        ..Default::default()
+                    },
+                };
+                println!("{analysis_resp:?}");
+                if let Err(err) = responder.send_analysis(analysis_resp).await {
+                    println!("cannot send response: {err}");
+                }
+                return;
+            }
+            let bot_resp = if bot_req.evaluation_request.is_some() {
+                // Evaluation requests never sleep and always reply.
+                let cache_key = eval_cache::EvaluationCache::key(&bot_req);
+                let option_cached_evaluation = evaluation_cache.lock().unwrap().get(&cache_key);
+                let eval_result = match option_cached_evaluation {
+                    Some(evaluation) => {
+                        println!("evaluation from cache");
+                        Ok(evaluation)
+                    }
+                    None => evaluate(EvaluateArguments {
+                        bot_req,
+                        game_state,
+                        tile_placer,
+                        kwg: &kwg,
+                        game_config: &game_config,
+                        klv: &klv,
+                        move_generator,
+                        is_jumbled,
+                        rack_reader: &rack_reader,
+                        play_reader: &play_reader,
+                    })
+                    .await
+                    .inspect(|evaluation| {
+                        evaluation_cache
+                            .lock()
+                            .unwrap()
+                            .insert(cache_key, evaluation.clone());
+                    }),
+                };
+                macondo::BotResponse {
+                    response: match &eval_result {
+                        Err(err) => Some(macondo::bot_response::Response::Error(err.to_string())),
+                        Ok(_) => None,
+                    },
+                    eval: eval_result.ok(),
+                    game_id: option_game_id.clone().unwrap_or("".to_string()),
+                    ..Default::default()
+                }
+            } else {
+                // Simming takes a while, as for analyses. The rng comes back for
+                // the delay.
+                let (game_event_result, returned_rng) = tokio::task::spawn_blocking(move || {
+                    let game_event_result = elucubrate(ElucubrateArguments {
+                        bot_req,
+                        tilter,
+                        game_state,
+                        tile_placer,
+                        kwg: &kwg,
+                        game_config: &game_config,
+                        klv: &klv,
+                        noleave_klv: &noleave_klv,
+                        move_generator,
+                        is_jumbled,
+                        rack_reader: &rack_reader,
+                        option_common_word_kwg,
+                        option_opening_book,
+                        pre_endgame_max_bag,
+                        rng: &mut rng,
+                    })
+                    .map_err(|err| err.to_string());
+                    (game_event_result, rng)
+                })
+                .await
+                .unwrap_or_else(|err| {
+                    (
+                        Err(err.to_string()),
+                        rand::rngs::ChaCha20Rng::seed_from_u64(seed),
+                    )
+                });
+                rng = returned_rng;
+
+                macondo::BotResponse {
+                    response: Some(match game_event_result {
+                        Ok(Ok(Elucubration {
+                            game_event,
+                            move_complexity,
+                            option_time_budget: ret_option_time_budget,
+                        })) => {
+                            option_move_complexity = Some(move_complexity);
+                            option_time_budget = ret_option_time_budget;
+                            macondo::bot_response::Response::Move(game_event)
+                        }
+                        Ok(Err(reason)) => {
+                            if responder.must_reply() {
+                                println!("{reason}, so only reporting it");
+                            } else {
+                                println!("{reason}, so not responding");
+                                should_reply = false;
+                            }
+                            macondo::bot_response::Response::Error(reason.into())
+                        }
+                        Err(err) => macondo::bot_response::Response::Error(err),
+                    }),
+                    game_id: option_game_id.clone().unwrap_or("".to_string()), // does not seem to be used by liwords
+                    ..Default::default()
+                }
+            };
+            if should_reply {
+                println!("{bot_resp:?}");
+                option_bot_resp = Some(bot_resp);
+            }
+        }
+        if let Some(bot_resp) = option_bot_resp {
+            let mut time_for_move_ms = match &option_move_complexity {
+                Some(move_complexity) => delay_model.delay_millis(
+                    move_complexity,
+                    option_game_id.as_deref().unwrap_or(""),
+                    rng.random_range(-1.0..=1.0),
+                ),
+                None => 0,
+            };
+            if time_for_move_ms > 0 {
+                if let Some(time_budget) = option_time_budget {
+                    // never sleep into a time penalty.
+                    time_for_move_ms =
+                        time_for_move_ms.min(time_budget.millis_for_move.max(0) as u128);
+                }
+                let elapsed_ms = msg_received_instant.elapsed().as_millis();
+                let sleep_for_ms = time_for_move_ms.saturating_sub(elapsed_ms) as u64;
+                println!("sleeping for {sleep_for_ms}ms");
+                tokio::time::sleep(tokio::time::Duration::from_millis(sleep_for_ms)).await;
+                println!("sending response");
+            } else {
+                println!("sending response immediately");
+            }
+
+            if let Err(err) = responder.send(bot_resp).await {
+                println!("cannot send response: {err}");
+            }
+        }
+    });
+}
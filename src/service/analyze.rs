@@ -0,0 +1,149 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// The ranked candidate moves for the player on turn, for the analysis tool and
+// for hints. For sim bots, the candidates are simmed, and ranked by that
+// instead.
+
+use super::{OmgBotType, analysis, encode, macondo, omg_bot_type, position, reconstruct, sim};
+use wolges::*;
+
+pub const NUM_CANDIDATES: usize = 10;
+const NUM_SIM_ITERATIONS: u32 = 100;
+// analyses have no clock, so simming stops after this long.
+const SIM_MILLIS: u64 = 5000;
+
+pub struct AnalyzeArguments<'a, N: kwg::Node> {
+    pub bot_req: Box<macondo::BotRequest>,
+    pub game_state: game_state::GameState,
+    pub tile_placer: position::TilePlacer,
+    pub kwg: &'a kwg::Kwg<N>,
+    pub game_config: &'a game_config::GameConfig,
+    pub klv: &'a klv::Klv<kwg::Node22>,
+    pub move_generator: movegen::KurniaMoveGenerator,
+    pub is_jumbled: bool,
+    pub rack_reader: &'a alphabet::AlphabetReader,
+    pub rng: &'a mut rand::rngs::ChaCha20Rng,
+}
+
+// the rack after the play, sorted.
+fn leave_of(rack: &[u8], play: &movegen::Play) -> Vec<u8> {
+    let mut leave = rack.to_vec();
+    let mut remove = |tile: u8| {
+        if let Some(i) = leave.iter().position(|&t| t == tile) {
+            leave.swap_remove(i);
+        }
+    };
+    match play {
+        movegen::Play::Exchange { tiles } => tiles.iter().for_each(|&tile| remove(tile)),
+        movegen::Play::Place { word, .. } => {
+            for &tile in word.iter() {
+                // 0 is a played-through tile, a designated blank is 0 on the rack.
+                if tile != 0 {
+                    remove(if tile & 0x80 != 0 { 0 } else { tile });
+                }
+            }
+        }
+    }
+    leave.sort_unstable();
+    leave
+}
+
+pub fn analyze<N: kwg::Node>(
+    AnalyzeArguments {
+        bot_req,
+        mut game_state,
+        mut tile_placer,
+        kwg,
+        game_config,
+        klv,
+        mut move_generator,
+        is_jumbled,
+        rack_reader,
+        rng,
+    }: AnalyzeArguments<'_, N>,
+) -> Result<Vec<analysis::Candidate>, Box<dyn std::error::Error>> {
+    let game_history = bot_req.game_history.as_ref().ok_or("no game history")?;
+    reconstruct::rebuild_position(
+        game_history,
+        &mut game_state,
+        &mut tile_placer,
+        kwg,
+        None,
+        game_config,
+        is_jumbled,
+        rack_reader,
+        rng,
+    )?;
+
+    let alphabet = game_config.alphabet();
+    let rack = &game_state.current_player().rack;
+    let board_snapshot = &movegen::BoardSnapshot {
+        board_tiles: &game_state.board_tiles,
+        game_config,
+        kwg,
+        klv,
+    };
+    let should_sim = matches!(omg_bot_type(bot_req.bot_type()).1, OmgBotType::Sim) && !is_jumbled;
+    let mut sim_stats = Vec::new();
+    if should_sim {
+        sim_stats = sim::pick_a_move(
+            &sim::SimParams {
+                num_candidates: NUM_CANDIDATES,
+                num_iterations: NUM_SIM_ITERATIONS,
+                ..Default::default()
+            },
+            &game_state,
+            game_config,
+            kwg,
+            klv,
+            &mut move_generator,
+            Some(std::time::Instant::now() + std::time::Duration::from_millis(SIM_MILLIS)),
+            rng,
+        )?;
+    } else {
+        let rack_size = game_config.rack_size() as usize;
+        let can_exchange = sim::num_tiles_in_bag(&game_state, rack_size) >= rack_size;
+        move_generator.gen_moves_filtered(
+            &movegen::GenMovesParams {
+                board_snapshot,
+                rack,
+                max_gen: NUM_CANDIDATES,
+                num_exchanges_by_this_player: 0,
+                always_include_pass: true,
+                dynamic_leaves: None,
+            },
+            |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+            |leave_value: i32| leave_value,
+            |_equity: equity::Equity, play: &movegen::Play| can_exchange || !sim::is_exchange(play),
+        );
+    }
+
+    let mut candidates = Vec::with_capacity(move_generator.plays.len());
+    for (i, valued_move) in move_generator.plays.iter().enumerate() {
+        let play = &valued_move.play;
+        let score = match play {
+            movegen::Play::Exchange { .. } => 0,
+            movegen::Play::Place { score, .. } => equity::descale_score(*score),
+        };
+        let equity = valued_move.equity.as_f64();
+        candidates.push(analysis::Candidate {
+            r#move: Some(encode::play_to_event(
+                play,
+                game_config,
+                &game_state.board_tiles,
+                rack,
+            )),
+            play: format!("{}", play.fmt(board_snapshot)),
+            score,
+            leave: format!("{}", alphabet.fmt_rack(&leave_of(rack, play))),
+            equity,
+            sim: sim_stats.get(i).map(|stats| analysis::SimStats {
+                iterations: stats.iterations,
+                mean: stats.mean,
+                stdev: stats.stdev,
+                ahead_pct: stats.ahead_pct,
+            }),
+        });
+    }
+    Ok(candidates)
+}
@@ -0,0 +1,229 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Opening books, one per lexicon, read from LEXICON.book if it is there. Each
+// line is a variant, a rack and the play for it, like
+//
+//   classic AEINRST 8D RETAINS
+//   classic IIIUUVW -IIIUUVW
+//
+// The play is in the engine's syntax. "book LEXICON VARIANT" makes these
+// lines for the racks on stdin, picked by the simming bot without a clock.
+// "book LEXICON VARIANT sim,..." picks with the rollouts in sim.rs instead,
+// with the settings given as for the tools' strategies, which can sim deeper.
+// Those rollouts are seeded from the master seed and the rack, so OMGBOT_SEED
+// makes the same book again; the bot's simmer is not seeded.
+
+use super::{
+    ArcKwgEither, Lexicons, RecycledStuffs, encode, macondo, parse_played_tiles, parse_rack,
+    position, seed, sim,
+};
+use rand::prelude::*;
+use wolges::*;
+
+// the variant names of a game history, where "" is classic.
+fn variant_key(variant: &str) -> &str {
+    if variant.is_empty() {
+        "classic"
+    } else {
+        variant
+    }
+}
+
+pub struct OpeningBook(std::collections::HashMap<(String, Vec<u8>), movegen::Play>);
+
+impl OpeningBook {
+    // game_config_of gives the game config for a variant, as the board size
+    // depends on it.
+    pub fn parse<'a, F: Fn(&str) -> Option<&'a game_config::GameConfig>>(
+        s: &str,
+        rack_reader: &alphabet::AlphabetReader,
+        play_reader: &alphabet::AlphabetReader,
+        game_config_of: F,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut book = std::collections::HashMap::new();
+        let mut rack = Vec::new();
+        let mut tiles = Vec::new();
+        for (line_idx, line) in s.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            let Some(variant) = tokens.next() else {
+                continue;
+            };
+            let at = |err: &dyn std::fmt::Display| format!("line {}: {err}", line_idx + 1);
+            let game_config = game_config_of(variant).ok_or_else(|| at(&"unknown variant"))?;
+            parse_rack(
+                rack_reader,
+                tokens.next().ok_or_else(|| at(&"no rack"))?,
+                &mut rack,
+            )
+            .map_err(|err| at(&err))?;
+            rack.sort_unstable();
+            let coord = tokens.next().ok_or_else(|| at(&"no play"))?;
+            let play = match coord.strip_prefix('-') {
+                Some(exchanged) => {
+                    parse_rack(rack_reader, exchanged, &mut tiles).map_err(|err| at(&err))?;
+                    tiles.sort_unstable();
+                    movegen::Play::Exchange {
+                        tiles: tiles[..].into(),
+                    }
+                }
+                None => {
+                    let word = tokens.next().ok_or_else(|| at(&"no word"))?;
+                    parse_played_tiles(play_reader, word, &mut tiles).map_err(|err| at(&err))?;
+                    let placement =
+                        position::Placement::of_coord(coord, "", game_config.board_layout().dim())
+                            .map_err(|err| at(&err))?;
+                    movegen::Play::Place {
+                        down: placement.down,
+                        lane: placement.lane,
+                        idx: placement.idx,
+                        word: tiles[..].into(),
+                        score: 0,
+                    }
+                }
+            };
+            book.insert((variant_key(variant).to_string(), rack.clone()), play);
+        }
+        Ok(Self(book))
+    }
+
+    // The book play for the rack, if there is one.
+    pub fn get(&self, variant: &str, rack: &[u8]) -> Option<&movegen::Play> {
+        let mut sorted_rack = rack.to_vec();
+        sorted_rack.sort_unstable();
+        self.0.get(&(variant_key(variant).to_string(), sorted_rack))
+    }
+}
+
+// Where the book play is among the generated plays, if it is there. A book play
+// that cannot be found is not legal, so it is never played.
+pub fn find(book_play: &movegen::Play, plays: &[movegen::ValuedMove]) -> Option<usize> {
+    let mut sorted_tiles = Vec::new();
+    plays
+        .iter()
+        .position(|valued_move| match (&valued_move.play, book_play) {
+            (
+                movegen::Play::Place {
+                    down,
+                    lane,
+                    idx,
+                    word,
+                    ..
+                },
+                movegen::Play::Place {
+                    down: book_down,
+                    lane: book_lane,
+                    idx: book_idx,
+                    word: book_word,
+                    ..
+                },
+            ) => down == book_down && lane == book_lane && idx == book_idx && word == book_word,
+            (movegen::Play::Exchange { tiles }, movegen::Play::Exchange { tiles: book_tiles }) => {
+                sorted_tiles.clear();
+                sorted_tiles.extend_from_slice(tiles);
+                sorted_tiles.sort_unstable();
+                sorted_tiles[..] == book_tiles[..]
+            }
+            _ => false,
+        })
+}
+
+// Reads a rack per line, prints a book line for each.
+pub fn run(
+    lexicons: &Lexicons,
+    lexicon: &str,
+    variant: &str,
+    option_sim_params: Option<sim::SimParams>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stuffs = lexicons.recycle(Box::new(macondo::BotRequest {
+        game_history: Some(macondo::GameHistory {
+            players: vec![Default::default(); 2],
+            lexicon: lexicon.into(),
+            variant: variant.into(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))?;
+    match &*stuffs.kwg {
+        ArcKwgEither::Node22(kwg) => run_with(&stuffs, kwg, variant, option_sim_params),
+        ArcKwgEither::Node24(kwg) => run_with(&stuffs, kwg, variant, option_sim_params),
+    }
+}
+
+fn run_with<N: kwg::Node + Sync + Send>(
+    stuffs: &RecycledStuffs,
+    kwg: &kwg::Kwg<N>,
+    variant: &str,
+    option_sim_params: Option<sim::SimParams>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let game_config = &stuffs.game_config;
+    let mut game_state = game_state::GameState::new(game_config);
+    let mut move_generator = movegen::KurniaMoveGenerator::new(game_config);
+    // stdout is the book.
+    let seeder = seed::Seeder::from_env()?;
+    eprintln!("master seed: {}", seeder.master_seed());
+    for line in std::io::stdin().lines() {
+        let line = line?;
+        let rack_str = line.trim();
+        if rack_str.is_empty() {
+            continue;
+        }
+        game_state.reset();
+        parse_rack(
+            &stuffs.rack_reader,
+            rack_str,
+            &mut game_state.players[0].rack,
+        )?;
+        position::fill_bag(game_config, &mut game_state)
+            .map_err(|err| format!("{rack_str}: {err}"))?;
+        match &option_sim_params {
+            Some(sim_params) => {
+                // the same rack gets the same play whatever else is in the input.
+                let mut rng =
+                    rand::rngs::ChaCha20Rng::seed_from_u64(seeder.request_seed(rack_str, 0));
+                sim::pick_a_move(
+                    sim_params,
+                    &game_state,
+                    game_config,
+                    kwg,
+                    &stuffs.klv,
+                    &mut move_generator,
+                    None,
+                    &mut rng,
+                )?;
+            }
+            None => {
+                move_picker::MovePicker::Simmer(move_picker::Simmer::new(
+                    game_config,
+                    kwg,
+                    &stuffs.klv,
+                ))
+                .pick_a_move(
+                    &mut move_filter::GenMoves::Unfiltered,
+                    &mut move_generator,
+                    &movegen::BoardSnapshot {
+                        board_tiles: &game_state.board_tiles,
+                        game_config,
+                        kwg,
+                        klv: &stuffs.klv,
+                    },
+                    &game_state,
+                    &game_state.players[0].rack,
+                );
+            }
+        }
+        let game_event = encode::play_to_event(
+            &move_generator.plays[0].play,
+            game_config,
+            &game_state.board_tiles,
+            &game_state.players[0].rack,
+        );
+        let play = match game_event.r#type() {
+            macondo::game_event::Type::TilePlacementMove => {
+                format!("{} {}", game_event.position, game_event.played_tiles)
+            }
+            _ => format!("-{}", game_event.exchanged),
+        };
+        println!("{} {rack_str} {play}", variant_key(variant));
+    }
+    Ok(())
+}
@@ -0,0 +1,165 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// How long the bot pretends to think before replying.
+
+use super::{macondo, seed};
+use wolges::*;
+
+pub enum MoveKind {
+    Challenge,
+    Pass,
+    Exchange,
+    Place,
+    Bingo,
+}
+
+pub struct MoveComplexity {
+    pub move_kind: MoveKind,
+    // there was nothing else to do.
+    pub is_forced: bool,
+    // other plays within CLOSE_EQUITY of the best one.
+    pub num_close_alternatives: usize,
+    // the best play is far ahead of the rest.
+    pub is_obvious: bool,
+}
+
+// in equity units, see equity::SCALE.
+pub const CLOSE_EQUITY: i32 = 3;
+pub const OBVIOUS_EQUITY: i32 = 20;
+// the most close alternatives that make a move slower.
+pub const MAX_ALTERNATIVES: usize = 10;
+
+impl MoveComplexity {
+    pub fn challenge() -> Self {
+        Self {
+            move_kind: MoveKind::Challenge,
+            is_forced: false,
+            num_close_alternatives: 0,
+            is_obvious: false,
+        }
+    }
+
+    // plays are the top plays by equity, best first, generated with the pass
+    // always included, so a lone play is the only legal move. Pickers may only
+    // generate the play they pick, so these have to be generated for this.
+    pub fn of_plays(move_kind: MoveKind, plays: &[movegen::ValuedMove]) -> Self {
+        let best_equity = plays[0].equity.raw();
+        Self {
+            move_kind,
+            is_forced: plays.len() == 1,
+            num_close_alternatives: plays[1..]
+                .iter()
+                .filter(|vm| best_equity - vm.equity.raw() <= CLOSE_EQUITY * equity::SCALE)
+                .count(),
+            is_obvious: plays.len() > 1
+                && best_equity - plays[1].equity.raw() > OBVIOUS_EQUITY * equity::SCALE,
+        }
+    }
+
+    // 1.0 for an ordinary play.
+    fn delay_factor(&self) -> f64 {
+        if self.is_forced {
+            return 0.2;
+        }
+        let kind_factor = match self.move_kind {
+            MoveKind::Challenge => 0.5,
+            MoveKind::Pass => 0.8,
+            MoveKind::Exchange => 1.4,
+            MoveKind::Place => 1.0,
+            MoveKind::Bingo => 1.5,
+        };
+        let choice_factor = if self.is_obvious {
+            0.6
+        } else {
+            1.0 + 0.1 * self.num_close_alternatives.min(MAX_ALTERNATIVES) as f64
+        };
+        kind_factor * choice_factor
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DelayModel {
+    pub base_millis: f64,
+    // each move randomly takes up to this fraction more or less.
+    pub spread: f64,
+}
+
+impl DelayModel {
+    const NONE: Self = Self {
+        base_millis: 0.0,
+        spread: 0.0,
+    };
+
+    // jitter is uniformly random in -1.0..=1.0.
+    pub fn delay_millis(
+        &self,
+        move_complexity: &MoveComplexity,
+        game_id: &str,
+        jitter: f64,
+    ) -> u128 {
+        (self.base_millis
+            * move_complexity.delay_factor()
+            * game_tempo(game_id)
+            * (1.0 + self.spread * jitter))
+            .max(0.0) as u128
+    }
+}
+
+// Some games are played a little faster than others. Same game, same tempo,
+// even across rust versions.
+fn game_tempo(game_id: &str) -> f64 {
+    0.85 + 0.3 * (seed::fnv1a(game_id.as_bytes().iter()) % 1001) as f64 / 1000.0
+}
+
+pub struct DelayModels(std::collections::HashMap<macondo::bot_request::BotCode, DelayModel>);
+
+impl DelayModels {
+    // The Tilt bots take a few seconds per move. Others reply immediately.
+    // OMGBOT_DELAYS overrides these, e.g. "HASTY_BOT=1000,LEVEL1_PROBABILISTIC=4000:0.4"
+    // means base millis of 1000 and 4000, with a spread of 0.4 for the latter.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let tilt_delay_model = DelayModel {
+            base_millis: 3000.0,
+            spread: 0.3,
+        };
+        let mut delay_models = std::collections::HashMap::new();
+        for bot_code in [
+            macondo::bot_request::BotCode::Level1CommonWordBot,
+            macondo::bot_request::BotCode::Level2CommonWordBot,
+            macondo::bot_request::BotCode::Level3CommonWordBot,
+            macondo::bot_request::BotCode::Level4CommonWordBot,
+            macondo::bot_request::BotCode::Level1Probabilistic,
+            macondo::bot_request::BotCode::Level2Probabilistic,
+            macondo::bot_request::BotCode::Level3Probabilistic,
+            macondo::bot_request::BotCode::Level4Probabilistic,
+            macondo::bot_request::BotCode::Level5Probabilistic,
+        ] {
+            delay_models.insert(bot_code, tilt_delay_model);
+        }
+        if let Ok(s) = std::env::var("OMGBOT_DELAYS") {
+            for item in s.split(',').filter(|item| !item.is_empty()) {
+                let (bot_code_name, delay) = item
+                    .split_once('=')
+                    .ok_or_else(|| format!("no = in OMGBOT_DELAYS item {item:?}"))?;
+                let bot_code = macondo::bot_request::BotCode::from_str_name(bot_code_name)
+                    .ok_or_else(|| format!("unknown bot code {bot_code_name:?}"))?;
+                let (base_millis, spread) = match delay.split_once(':') {
+                    Some((base_millis, spread)) => (base_millis.parse()?, spread.parse()?),
+                    None => (delay.parse()?, tilt_delay_model.spread),
+                };
+                delay_models.insert(
+                    bot_code,
+                    DelayModel {
+                        base_millis,
+                        spread,
+                    },
+                );
+            }
+        }
+        Ok(Self(delay_models))
+    }
+
+    pub fn get(&self, bot_code: macondo::bot_request::BotCode) -> DelayModel {
+        self.0.get(&bot_code).copied().unwrap_or(DelayModel::NONE)
+    }
+}
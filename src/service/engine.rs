@@ -0,0 +1,522 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// A line protocol on stdin and stdout for analysis GUIs and scripts, in the
+// manner of chess engines. Each command is answered by some lines of text and
+// then "ok", or by a single "error ..." line.
+//
+//   newgame LEXICON [VARIANT]  empty board, full bag, random racks
+//   position cgp CGP           the racks and scores in the cgp are given with
+//                              the player to move first
+//   play 8D QUEEN              for the player on turn, letters may be given
+//                              for played-through tiles. "-" passes, "-ABC"
+//                              exchanges.
+//   go [bot=CODE] [time=MS]    "bestmove ..." for the player on turn. the
+//                              simming bot plays its best equity play if
+//                              the sim takes longer than MS.
+//   eval [N]                   the top N plays by equity, 10 by default
+//   show                       the board, racks and bag
+//   quit
+//
+// Each game shuffles and draws with a seed from the master seed and how many
+// games came before it, so OMGBOT_SEED replays a session, except for the
+// simmer's picks.
+
+use super::{
+    ArcKwgEither, Lexicons, OmgBotType, RecycledStuffs, SIM_MIN_MILLIS_FOR_MOVE, macondo,
+    omg_bot_type, parse_played_tiles, parse_rack, position, seed, sim, sim_until,
+};
+use rand::prelude::*;
+use wolges::*;
+
+const DEFAULT_NUM_EVAL_PLAYS: usize = 10;
+
+struct Game {
+    variant: String,
+    stuffs: RecycledStuffs,
+    game_state: game_state::GameState,
+    move_generator: movegen::KurniaMoveGenerator,
+    tile_placer: position::TilePlacer,
+    rng: rand::rngs::ChaCha20Rng,
+}
+
+fn new_game(
+    lexicons: &Lexicons,
+    lexicon: &str,
+    variant: &str,
+    rng_seed: u64,
+) -> Result<Game, Box<dyn std::error::Error>> {
+    let stuffs = lexicons.recycle(Box::new(macondo::BotRequest {
+        game_history: Some(macondo::GameHistory {
+            players: vec![Default::default(); 2],
+            lexicon: lexicon.into(),
+            variant: variant.into(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))?;
+    let mut game_state = game_state::GameState::new(&stuffs.game_config);
+    game_state.reset();
+    let move_generator = movegen::KurniaMoveGenerator::new(&stuffs.game_config);
+    let tile_placer = position::TilePlacer::new(
+        std::sync::Arc::clone(&stuffs.game_config),
+        std::sync::Arc::clone(&stuffs.play_reader),
+    );
+    Ok(Game {
+        variant: variant.into(),
+        stuffs,
+        game_state,
+        move_generator,
+        tile_placer,
+        rng: rand::rngs::ChaCha20Rng::seed_from_u64(rng_seed),
+    })
+}
+
+impl Game {
+    // every tile not on the board or on a rack, shuffled.
+    fn fill_bag(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        position::fill_bag(&self.stuffs.game_config, &mut self.game_state)?;
+        self.game_state.bag.shuffle(&mut self.rng);
+        Ok(())
+    }
+
+    fn draw_tiles(&mut self, player_idx: usize) {
+        let rack_size = self.stuffs.game_config.rack_size() as usize;
+        let rack = &mut self.game_state.players[player_idx].rack;
+        while rack.len() < rack_size
+            && let Some(tile) = self.game_state.bag.0.pop()
+        {
+            rack.push(tile);
+        }
+    }
+
+    // fields: board, racks, scores, consecutive zero-point turns, then opcodes.
+    fn set_cgp(&mut self, cgp: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut fields = cgp.split_whitespace();
+        let board = fields.next().ok_or("no board")?;
+        let racks = fields.next().ok_or("no racks")?;
+        let scores = fields.next().ok_or("no scores")?;
+        fields.next().ok_or("no consecutive zero-point turns")?;
+
+        let dim = self.stuffs.game_config.board_layout().dim();
+        self.game_state.reset();
+        let rows = board.split('/').collect::<Vec<_>>();
+        if rows.len() != dim.rows as usize {
+            wolges::return_error!(format!("need {} rows", dim.rows));
+        }
+        for (row_idx, row) in rows.iter().enumerate() {
+            let sb = row.as_bytes();
+            let mut ix = 0;
+            let mut col_idx = 0;
+            while ix < sb.len() {
+                if sb[ix].is_ascii_digit() {
+                    let end_ix = sb[ix..]
+                        .iter()
+                        .position(|c| !c.is_ascii_digit())
+                        .map_or(sb.len(), |len| ix + len);
+                    col_idx += row[ix..end_ix].parse::<usize>()?;
+                    ix = end_ix;
+                } else if let Some((tile, end_ix)) = self.stuffs.play_reader.next_tile(sb, ix)
+                    && col_idx < dim.cols as usize
+                {
+                    self.game_state.board_tiles[row_idx * dim.cols as usize + col_idx] = tile;
+                    col_idx += 1;
+                    ix = end_ix;
+                } else {
+                    wolges::return_error!(format!("invalid row {}: {row:?}", row_idx + 1));
+                }
+            }
+            if col_idx != dim.cols as usize {
+                wolges::return_error!(format!("row {} is not {} long", row_idx + 1, dim.cols));
+            }
+        }
+
+        let racks = racks.split('/').collect::<Vec<_>>();
+        let scores = scores.split('/').collect::<Vec<_>>();
+        if racks.len() != 2 || scores.len() != 2 {
+            wolges::return_error!("need two racks and two scores".into());
+        }
+        for player_idx in 0..2 {
+            let player = &mut self.game_state.players[player_idx];
+            parse_rack(
+                &self.stuffs.rack_reader,
+                racks[player_idx],
+                &mut player.rack,
+            )?;
+            player.score = scores[player_idx].parse()?;
+        }
+        self.game_state.turn = 0;
+        self.fill_bag()
+    }
+
+    // the letters of played-through tiles become '.', as place_tiles expects.
+    fn play_place(&mut self, coord: &str, word: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let game_config = &self.stuffs.game_config;
+        let alphabet = game_config.alphabet();
+        let dim = game_config.board_layout().dim();
+        let mut tiles = Vec::new();
+        parse_played_tiles(&self.stuffs.play_reader, word, &mut tiles)?;
+        let position::Placement {
+            down, lane, idx, ..
+        } = position::Placement::of_coord(coord, "", dim)?;
+        let strider = dim.lane(down, lane);
+        if idx as usize + tiles.len() > strider.len() as usize {
+            wolges::return_error!("out of bounds".into());
+        }
+        let mut played_tiles = String::new();
+        let mut rack = self.game_state.current_player().rack.clone();
+        let mut bag = self.game_state.bag.0.clone();
+        for (i, &tile) in (idx..).zip(tiles.iter()) {
+            let board_tile = self.game_state.board_tiles[strider.at(i)];
+            if board_tile != 0 {
+                if tile != 0 && tile & 0x7f != board_tile & 0x7f {
+                    wolges::return_error!(format!("{word} does not match the board"));
+                }
+                played_tiles.push('.');
+            } else if tile == 0 {
+                played_tiles.push('.');
+            } else {
+                played_tiles.push_str(alphabet.of_board(tile).ok_or("invalid tile")?);
+                // a tile not on a known rack comes from the unseen tiles.
+                let rack_tile = if tile & 0x80 != 0 { 0 } else { tile };
+                if let Some(pos) = rack.iter().position(|&t| t == rack_tile) {
+                    rack.swap_remove(pos);
+                } else if let Some(pos) = bag.iter().position(|&t| t == rack_tile) {
+                    bag.swap_remove(pos);
+                } else {
+                    wolges::return_error!(format!("no more of tile {rack_tile}"));
+                }
+            }
+        }
+        let mut board_tiles = self.game_state.board_tiles.clone();
+        let score = self.tile_placer.place(
+            &mut board_tiles,
+            &position::Placement {
+                down,
+                lane,
+                idx,
+                played_tiles: &played_tiles,
+                option_score: None,
+            },
+        )?;
+        println!("score {score}");
+        self.game_state.board_tiles = board_tiles;
+        self.game_state.bag.0 = bag;
+        let turn = self.game_state.turn as usize;
+        let player = &mut self.game_state.players[turn];
+        player.rack = rack;
+        player.score += score;
+        self.draw_tiles(turn);
+        self.game_state.next_turn();
+        Ok(())
+    }
+
+    fn play_exchange(&mut self, exchanged: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tiles = Vec::new();
+        parse_rack(&self.stuffs.rack_reader, exchanged, &mut tiles)?;
+        if tiles.len() > self.game_state.bag.0.len() {
+            wolges::return_error!("not enough tiles in the bag".into());
+        }
+        let turn = self.game_state.turn as usize;
+        let mut rack = self.game_state.players[turn].rack.clone();
+        for &tile in tiles.iter() {
+            let pos = rack
+                .iter()
+                .position(|&t| t == tile)
+                .ok_or(format!("{exchanged} is not on the rack"))?;
+            rack.swap_remove(pos);
+        }
+        self.game_state.players[turn].rack = rack;
+        self.draw_tiles(turn);
+        self.game_state.bag.0.extend_from_slice(&tiles);
+        self.game_state.bag.shuffle(&mut self.rng);
+        self.game_state.next_turn();
+        Ok(())
+    }
+
+    fn go<N: kwg::Node + Sync + Send + 'static>(
+        &mut self,
+        kwg: &std::sync::Arc<kwg::Kwg<N>>,
+        option_common_word_kwg: Option<&std::sync::Arc<kwg::Kwg<N>>>,
+        noleave_klv: &klv::Klv<kwg::Node22>,
+        bot_code: macondo::bot_request::BotCode,
+        option_time_ms: Option<i32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let game_config = &self.stuffs.game_config;
+        let is_jumbled = match game_config.game_rules() {
+            game_config::GameRules::Classic => false,
+            game_config::GameRules::Jumbled => true,
+        };
+        let klv: &klv::Klv<kwg::Node22> = match bot_code {
+            macondo::bot_request::BotCode::NoLeaveBot => noleave_klv,
+            _ => &self.stuffs.klv,
+        };
+        let (use_common_word, effective_bot_type) = omg_bot_type(bot_code);
+        let used_kwg = if use_common_word {
+            option_common_word_kwg.ok_or("common word lexicon unavailable")?
+        } else {
+            kwg
+        };
+        let (mut move_filter, is_simming) = match effective_bot_type {
+            OmgBotType::Tilt(bot_level) if self.stuffs.tilter.is_some() && !is_jumbled => {
+                let mut tilt = self.stuffs.tilter.clone().unwrap();
+                tilt.tilt_by_rng(&mut self.rng, bot_level);
+                (move_filter::GenMoves::Tilt { tilt, bot_level }, false)
+            }
+            OmgBotType::Sim
+                if !is_jumbled
+                    && option_time_ms.is_none_or(|time_ms| time_ms >= SIM_MIN_MILLIS_FOR_MOVE) =>
+            {
+                (move_filter::GenMoves::Unfiltered, true)
+            }
+            _ => (move_filter::GenMoves::Unfiltered, false),
+        };
+        let board_snapshot = &movegen::BoardSnapshot {
+            board_tiles: &self.game_state.board_tiles,
+            game_config,
+            kwg: used_kwg,
+            klv,
+        };
+        let option_deadline = option_time_ms.map(|time_ms| {
+            std::time::Instant::now() + std::time::Duration::from_millis(time_ms as u64)
+        });
+        let is_simmed = is_simming && {
+            // the other racks are not known to the player on turn.
+            let mut sim_state = self.game_state.clone();
+            sim::unsee_other_racks(&mut sim_state);
+            sim_until(
+                game_config,
+                used_kwg,
+                &self.stuffs.klv,
+                &sim_state,
+                &mut self.move_generator,
+                option_deadline,
+            )
+        };
+        if !is_simmed {
+            move_picker::MovePicker::Hasty.pick_a_move(
+                &mut move_filter,
+                &mut self.move_generator,
+                board_snapshot,
+                &self.game_state,
+                &self.game_state.current_player().rack,
+            );
+        }
+        let best = &self.move_generator.plays[0];
+        println!("bestmove {}", best.play.fmt(board_snapshot));
+        println!("equity {:.3}", best.equity.as_f64());
+        Ok(())
+    }
+
+    fn eval<N: kwg::Node + Sync + Send>(
+        &mut self,
+        kwg: &kwg::Kwg<N>,
+        num_plays: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let board_snapshot = &movegen::BoardSnapshot {
+            board_tiles: &self.game_state.board_tiles,
+            game_config: &self.stuffs.game_config,
+            kwg,
+            klv: &self.stuffs.klv,
+        };
+        self.move_generator.gen_moves_filtered(
+            &movegen::GenMovesParams {
+                board_snapshot,
+                rack: &self.game_state.current_player().rack,
+                max_gen: num_plays,
+                num_exchanges_by_this_player: 0,
+                always_include_pass: true,
+                dynamic_leaves: None,
+            },
+            |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+            |leave_value: i32| leave_value,
+            |_equity: equity::Equity, _play: &movegen::Play| true,
+        );
+        for (i, valued_move) in self.move_generator.plays.iter().enumerate() {
+            println!(
+                "{}. {} {:.3}",
+                i + 1,
+                valued_move.play.fmt(board_snapshot),
+                valued_move.equity.as_f64()
+            );
+        }
+        Ok(())
+    }
+}
+
+// the first argument of the opcode, which come after the four fields.
+fn cgp_opcode<'a>(cgp: &'a str, name: &str) -> Option<&'a str> {
+    cgp.split(';').enumerate().find_map(|(i, chunk)| {
+        let mut tokens = chunk.split_whitespace().skip(if i == 0 { 4 } else { 0 });
+        (tokens.next() == Some(name))
+            .then(|| tokens.next())
+            .flatten()
+    })
+}
+
+// the protobuf names, or like hasty, level3, common2, noleave, sim.
+fn parse_bot_code(name: &str) -> Option<macondo::bot_request::BotCode> {
+    if let Some(bot_code) = macondo::bot_request::BotCode::from_str_name(name) {
+        return Some(bot_code);
+    }
+    Some(match name {
+        "hasty" => macondo::bot_request::BotCode::HastyBot,
+        "noleave" => macondo::bot_request::BotCode::NoLeaveBot,
+        "sim" => macondo::bot_request::BotCode::SimmingBot,
+        "level1" => macondo::bot_request::BotCode::Level1Probabilistic,
+        "level2" => macondo::bot_request::BotCode::Level2Probabilistic,
+        "level3" => macondo::bot_request::BotCode::Level3Probabilistic,
+        "level4" => macondo::bot_request::BotCode::Level4Probabilistic,
+        "level5" => macondo::bot_request::BotCode::Level5Probabilistic,
+        "common1" => macondo::bot_request::BotCode::Level1CommonWordBot,
+        "common2" => macondo::bot_request::BotCode::Level2CommonWordBot,
+        "common3" => macondo::bot_request::BotCode::Level3CommonWordBot,
+        "common4" => macondo::bot_request::BotCode::Level4CommonWordBot,
+        _ => return None,
+    })
+}
+
+fn execute(
+    option_game: &mut Option<Game>,
+    num_games: &mut usize,
+    seeder: &seed::Seeder,
+    lexicons: &Lexicons,
+    noleave_klv: &klv::Klv<kwg::Node22>,
+    command: &str,
+    args: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if command == "newgame" {
+        let mut args = args.split_whitespace();
+        let lexicon = args.next().ok_or("need a lexicon")?;
+        let mut game = new_game(
+            lexicons,
+            lexicon,
+            args.next().unwrap_or(""),
+            seeder.request_seed("engine", *num_games),
+        )?;
+        *num_games += 1;
+        game.fill_bag()?;
+        game.draw_tiles(0);
+        game.draw_tiles(1);
+        *option_game = Some(game);
+        return Ok(());
+    }
+    if command == "position" {
+        let cgp = args
+            .strip_prefix("cgp ")
+            .ok_or("only position cgp is supported")?;
+        // the lex opcode switches lexicons.
+        if let Some(lexicon) = cgp_opcode(cgp, "lex") {
+            let variant = option_game.as_ref().map_or("", |game| &game.variant);
+            *option_game = Some(new_game(
+                lexicons,
+                lexicon,
+                variant,
+                seeder.request_seed("engine", *num_games),
+            )?);
+            *num_games += 1;
+        }
+        return option_game
+            .as_mut()
+            .ok_or("need a lexicon, from newgame or lex")?
+            .set_cgp(cgp);
+    }
+    let game = option_game.as_mut().ok_or("need newgame or position")?;
+    match command {
+        "play" => match args.strip_prefix('-') {
+            Some("") => {
+                game.game_state.next_turn();
+                Ok(())
+            }
+            Some(exchanged) => game.play_exchange(exchanged),
+            None => {
+                let (coord, word) = args.split_once(' ').ok_or("need a coord and a word")?;
+                game.play_place(coord, word.trim())
+            }
+        },
+        "go" => {
+            let mut bot_code = macondo::bot_request::BotCode::HastyBot;
+            let mut option_time_ms = None;
+            for arg in args.split_whitespace() {
+                match arg.split_once('=') {
+                    Some(("bot", name)) => {
+                        bot_code = parse_bot_code(name).ok_or(format!("unknown bot {name:?}"))?;
+                    }
+                    Some(("time", time_ms)) => option_time_ms = Some(time_ms.parse()?),
+                    _ => wolges::return_error!(format!("unknown option {arg:?}")),
+                }
+            }
+            let kwg = std::sync::Arc::clone(&game.stuffs.kwg);
+            let option_common_word_kwg = game.stuffs.option_common_word_kwg.clone();
+            match (&*kwg, option_common_word_kwg.as_deref()) {
+                (ArcKwgEither::Node22(kwg), option_common_word_kwg) => game.go(
+                    kwg,
+                    match option_common_word_kwg {
+                        Some(ArcKwgEither::Node22(common_word_kwg)) => Some(common_word_kwg),
+                        _ => None,
+                    },
+                    noleave_klv,
+                    bot_code,
+                    option_time_ms,
+                ),
+                (ArcKwgEither::Node24(kwg), option_common_word_kwg) => game.go(
+                    kwg,
+                    match option_common_word_kwg {
+                        Some(ArcKwgEither::Node24(common_word_kwg)) => Some(common_word_kwg),
+                        _ => None,
+                    },
+                    noleave_klv,
+                    bot_code,
+                    option_time_ms,
+                ),
+            }
+        }
+        "eval" => {
+            let num_plays = match args {
+                "" => DEFAULT_NUM_EVAL_PLAYS,
+                _ => args.parse()?,
+            };
+            let kwg = std::sync::Arc::clone(&game.stuffs.kwg);
+            match &*kwg {
+                ArcKwgEither::Node22(kwg) => game.eval(kwg, num_plays),
+                ArcKwgEither::Node24(kwg) => game.eval(kwg, num_plays),
+            }
+        }
+        "show" => {
+            display::print_game_state(&game.stuffs.game_config, &game.game_state, None);
+            Ok(())
+        }
+        _ => wolges::return_error!(format!("unknown command {command:?}")),
+    }
+}
+
+pub fn run(lexicons: &Lexicons) -> Result<(), Box<dyn std::error::Error>> {
+    // stdout is for the protocol.
+    let seeder = seed::Seeder::from_env()?;
+    eprintln!("master seed: {}", seeder.master_seed());
+    let mut option_game = None;
+    let mut num_games = 0;
+    for line in std::io::stdin().lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        if command == "quit" {
+            break;
+        }
+        match execute(
+            &mut option_game,
+            &mut num_games,
+            &seeder,
+            lexicons,
+            &lexicons.noleave_klv,
+            command,
+            args.trim(),
+        ) {
+            Ok(()) => println!("ok"),
+            Err(err) => println!("error {err}"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,91 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Evaluations of games already evaluated, as the analysis tool asks for the
+// same finished game again and again. The evaluations depend on the kwg and klv,
+// so each Lexicons::load makes its own cache, and nothing replaces the lexicons
+// of a Lexicons in place.
+
+use super::macondo;
+
+use prost::Message;
+
+pub struct EvaluationCache {
+    capacity: usize,
+    // by key, the evaluation and when it was last used.
+    entries: std::collections::HashMap<Vec<u8>, (macondo::Evaluation, u64)>,
+    clock: u64,
+}
+
+impl EvaluationCache {
+    // OMGBOT_EVAL_CACHE overrides the default of 256 games. 0 turns it off.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let capacity = match std::env::var("OMGBOT_EVAL_CACHE") {
+            Ok(s) => s
+                .parse()
+                .map_err(|err| format!("bad OMGBOT_EVAL_CACHE {s:?}: {err}"))?,
+            Err(_) => 256,
+        };
+        Ok(Self {
+            capacity,
+            entries: std::collections::HashMap::new(),
+            clock: 0,
+        })
+    }
+
+    // The events, lexicon, variant and players decide the evaluation, and so
+    // does the evaluated user. The key is all of them, not a hash, so different
+    // games never share an entry. Each part is prefixed by its length.
+    pub fn key(bot_req: &macondo::BotRequest) -> Vec<u8> {
+        fn add(key: &mut Vec<u8>, bytes: &[u8]) {
+            key.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            key.extend_from_slice(bytes);
+        }
+        let mut key = Vec::new();
+        if let Some(game_history) = &bot_req.game_history {
+            add(&mut key, game_history.lexicon.as_bytes());
+            add(&mut key, game_history.variant.as_bytes());
+            add(&mut key, &(game_history.players.len() as u64).to_le_bytes());
+            for player in game_history.players.iter() {
+                add(&mut key, &player.encode_to_vec());
+            }
+            for event in game_history.events.iter() {
+                add(&mut key, &event.encode_to_vec());
+            }
+        }
+        // after the events, so no event can be taken for the user.
+        match &bot_req.evaluation_request {
+            Some(evaluation_request) => {
+                key.push(1);
+                add(&mut key, evaluation_request.user.as_bytes());
+            }
+            None => key.push(0),
+        }
+        key
+    }
+
+    pub fn get(&mut self, key: &[u8]) -> Option<macondo::Evaluation> {
+        self.clock += 1;
+        let (evaluation, last_used) = self.entries.get_mut(key)?;
+        *last_used = self.clock;
+        Some(evaluation.clone())
+    }
+
+    // Makes room by dropping the least recently used.
+    pub fn insert(&mut self, key: Vec<u8>, evaluation: macondo::Evaluation) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        if self.entries.len() >= self.capacity
+            && !self.entries.contains_key(&key)
+            && let Some(oldest_key) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone())
+        {
+            self.entries.remove(&oldest_key);
+        }
+        self.entries.insert(key, (evaluation, self.clock));
+    }
+}
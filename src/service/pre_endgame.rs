@@ -0,0 +1,319 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// With only a few tiles left in the bag, tries every way the unseen tiles can
+// be split between the bag and the opponent's rack. With both racks known,
+// every candidate is valued by the spread it leads to, searching a few plays
+// ahead through the rest of the bag and the endgame. The search only looks at
+// the highest-scoring plays and stops after a few turns, so this is a better
+// guess than equity, not a solved endgame. There must be exactly one opponent.
+
+use super::sim;
+use wolges::*;
+
+const NUM_CANDIDATES: usize = 10;
+// the endgame search looks at this many plays per turn, highest score first,
+// this many turns ahead.
+const ENDGAME_BRANCHING: usize = 6;
+const ENDGAME_DEPTH: usize = 3;
+
+// the draws to try grow quickly with the tiles in the bag.
+const MAX_BAG_TILES: usize = 2;
+
+// How many tiles may be in the bag for simming bots to use this.
+// OMGBOT_PRE_ENDGAME_TILES overrides the default of 1, up to MAX_BAG_TILES.
+// 0 turns it off.
+pub fn max_bag_tiles_from_env() -> Result<usize, Box<dyn std::error::Error>> {
+    match std::env::var("OMGBOT_PRE_ENDGAME_TILES") {
+        Ok(s) => {
+            let max_bag_tiles = s
+                .parse()
+                .map_err(|err| format!("bad OMGBOT_PRE_ENDGAME_TILES {s:?}: {err}"))?;
+            if max_bag_tiles > MAX_BAG_TILES {
+                wolges::return_error!(format!(
+                    "OMGBOT_PRE_ENDGAME_TILES {max_bag_tiles} is more than {MAX_BAG_TILES}"
+                ));
+            }
+            Ok(max_bag_tiles)
+        }
+        Err(_) => Ok(1),
+    }
+}
+
+fn rack_value(alphabet: &alphabet::Alphabet, rack: &[u8]) -> i32 {
+    rack.iter().map(|&tile| alphabet.score(tile) as i32).sum()
+}
+
+fn play_score(play: &movegen::Play) -> i32 {
+    match play {
+        movegen::Play::Exchange { .. } => 0,
+        movegen::Play::Place { score, .. } => equity::descale_score(*score),
+    }
+}
+
+fn num_played(play: &movegen::Play) -> usize {
+    match play {
+        movegen::Play::Exchange { .. } => 0,
+        movegen::Play::Place { word, .. } => word.iter().filter(|&&tile| tile != 0).count(),
+    }
+}
+
+struct Searcher<'a, N: kwg::Node> {
+    game_config: &'a game_config::GameConfig,
+    kwg: &'a kwg::Kwg<N>,
+    klv: &'a klv::Klv<kwg::Node22>,
+    move_generator: movegen::KurniaMoveGenerator,
+}
+
+impl<N: kwg::Node> Searcher<'_, N> {
+    // the plays for the player on turn, by score. no exchanges, the bag has
+    // too few tiles.
+    fn endgame_plays(
+        &mut self,
+        game_state: &game_state::GameState,
+        max_gen: usize,
+    ) -> Vec<movegen::Play> {
+        self.move_generator.gen_moves_filtered(
+            &movegen::GenMovesParams {
+                board_snapshot: &movegen::BoardSnapshot {
+                    board_tiles: &game_state.board_tiles,
+                    game_config: self.game_config,
+                    kwg: self.kwg,
+                    klv: self.klv,
+                },
+                rack: &game_state.current_player().rack,
+                max_gen,
+                num_exchanges_by_this_player: 0,
+                always_include_pass: true,
+                dynamic_leaves: None,
+            },
+            |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+            |_leave_value: i32| 0,
+            |_equity: equity::Equity, play: &movegen::Play| {
+                !matches!(play, movegen::Play::Exchange { tiles } if !tiles.is_empty())
+            },
+        );
+        self.move_generator
+            .plays
+            .iter()
+            .map(|valued_move| valued_move.play.clone())
+            .collect()
+    }
+
+    // Spread the player on turn can still make, from here to the end or as
+    // far as depth goes. Going out gets twice the opponent's rack. After two
+    // passes in a row with the bag empty, everyone loses what is on their rack.
+    fn negamax<R: rand::Rng>(
+        &mut self,
+        game_state: &game_state::GameState,
+        depth: usize,
+        num_passes: usize,
+        mut alpha: i32,
+        beta: i32,
+        rng: &mut R,
+    ) -> Result<i32, Box<dyn std::error::Error>> {
+        let alphabet = self.game_config.alphabet();
+        let me = game_state.turn as usize;
+        let other = (me + 1) % game_state.players.len();
+        let plays = self.endgame_plays(game_state, ENDGAME_BRANCHING);
+        if depth == 0 {
+            return Ok(plays.first().map_or(0, play_score));
+        }
+        let mut best = i32::MIN + 1;
+        let mut child_state = game_state.clone();
+        for play in plays.iter() {
+            let score = play_score(play);
+            let is_bag_empty = game_state.bag.is_empty();
+            let value = if is_bag_empty && num_played(play) == game_state.players[me].rack.len() {
+                score + 2 * rack_value(alphabet, &game_state.players[other].rack)
+            } else if is_bag_empty && num_played(play) == 0 && num_passes >= 1 {
+                rack_value(alphabet, &game_state.players[other].rack)
+                    - rack_value(alphabet, &game_state.players[me].rack)
+            } else {
+                child_state.clone_from(game_state);
+                child_state.play(self.game_config, rng, play)?;
+                child_state.next_turn();
+                score
+                    - self.negamax(
+                        &child_state,
+                        depth - 1,
+                        if is_bag_empty && num_played(play) == 0 {
+                            num_passes + 1
+                        } else {
+                            0
+                        },
+                        -beta,
+                        -alpha,
+                        rng,
+                    )?
+            };
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Ok(best)
+    }
+}
+
+// Every multiset of num_tiles tiles from the tally, with how many ways there
+// are to draw it.
+fn each_draw<F: FnMut(&[u8], u64) -> Result<(), Box<dyn std::error::Error>>>(
+    tally: &[u8],
+    num_tiles: usize,
+    f: &mut F,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fn choose(n: u8, k: u8) -> u64 {
+        (0..k as u64).fold(1, |acc, i| acc * (n as u64 - i) / (i + 1))
+    }
+    fn iter<F: FnMut(&[u8], u64) -> Result<(), Box<dyn std::error::Error>>>(
+        tally: &[u8],
+        tile: usize,
+        num_tiles: usize,
+        ways: u64,
+        drawn: &mut Vec<u8>,
+        f: &mut F,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if num_tiles == 0 {
+            return f(drawn, ways);
+        }
+        if tile >= tally.len() {
+            return Ok(());
+        }
+        for k in 0..=(tally[tile] as usize).min(num_tiles) {
+            drawn.extend(std::iter::repeat_n(tile as u8, k));
+            iter(
+                tally,
+                tile + 1,
+                num_tiles - k,
+                ways * choose(tally[tile], k as u8),
+                drawn,
+                f,
+            )?;
+            drawn.truncate(drawn.len() - k);
+        }
+        Ok(())
+    }
+    iter(tally, 0, num_tiles, 1, &mut Vec::new(), f)
+}
+
+// Leaves the candidates in move_generator.plays, with the one with the best
+// expected spread first. game_state is for the player on turn. Returns false,
+// with the candidates by equity, if the deadline passes before every candidate
+// is valued.
+pub fn pick_a_move<N: kwg::Node, R: rand::Rng>(
+    game_state: &game_state::GameState,
+    game_config: &game_config::GameConfig,
+    kwg: &kwg::Kwg<N>,
+    klv: &klv::Klv<kwg::Node22>,
+    move_generator: &mut movegen::KurniaMoveGenerator,
+    option_deadline: Option<std::time::Instant>,
+    rng: &mut R,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if game_state.players.len() != 2 {
+        wolges::return_error!("pre-endgames need two players".into());
+    }
+    let rack_size = game_config.rack_size() as usize;
+    let num_in_bag = sim::num_tiles_in_bag(game_state, rack_size);
+    let me = game_state.turn as usize;
+    let opponent = (me + 1) % game_state.players.len();
+    let rack = &game_state.current_player().rack;
+    // there are too few tiles in the bag to exchange.
+    move_generator.gen_moves_filtered(
+        &movegen::GenMovesParams {
+            board_snapshot: &movegen::BoardSnapshot {
+                board_tiles: &game_state.board_tiles,
+                game_config,
+                kwg,
+                klv,
+            },
+            rack,
+            max_gen: NUM_CANDIDATES,
+            num_exchanges_by_this_player: 0,
+            always_include_pass: true,
+            dynamic_leaves: None,
+        },
+        |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+        |leave_value: i32| leave_value,
+        |_equity: equity::Equity, play: &movegen::Play| {
+            !matches!(play, movegen::Play::Exchange { tiles } if !tiles.is_empty())
+        },
+    );
+
+    let alphabet = game_config.alphabet();
+    let mut tally = vec![0u8; alphabet.len() as usize];
+    for &tile in game_state.bag.0.iter() {
+        tally[tile as usize] += 1;
+    }
+    let mut searcher = Searcher {
+        game_config,
+        kwg,
+        klv,
+        move_generator: movegen::KurniaMoveGenerator::new(game_config),
+    };
+    let mut endgame_state = game_state.clone();
+    let is_past_deadline =
+        || option_deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline);
+    let mut is_out_of_time = false;
+    let mut expected_values = Vec::with_capacity(move_generator.plays.len());
+    for valued_move in move_generator.plays.iter() {
+        let play = &valued_move.play;
+        // the bag holds drawn and the opponent has the rest of the unseen
+        // tiles. a play that leaves some in the bag draws a random few.
+        let mut total_value = 0.0;
+        let mut total_ways = 0u64;
+        each_draw(&tally, num_in_bag, &mut |drawn, ways| {
+            if is_out_of_time || is_past_deadline() {
+                is_out_of_time = true;
+                return Ok(());
+            }
+            endgame_state.clone_from(game_state);
+            let mut opponent_tally = tally.clone();
+            for &tile in drawn {
+                opponent_tally[tile as usize] -= 1;
+            }
+            endgame_state.bag.0.clear();
+            endgame_state.bag.0.extend_from_slice(drawn);
+            endgame_state.players[opponent].rack.extend(
+                (0u8..)
+                    .zip(opponent_tally.iter())
+                    .flat_map(|(tile, &count)| std::iter::repeat_n(tile, count as usize)),
+            );
+            endgame_state.play(game_config, rng, play)?;
+            let value = if endgame_state.bag.is_empty() && endgame_state.players[me].rack.is_empty()
+            {
+                play_score(play) + 2 * rack_value(alphabet, &endgame_state.players[opponent].rack)
+            } else {
+                endgame_state.next_turn();
+                play_score(play)
+                    - searcher.negamax(
+                        &endgame_state,
+                        ENDGAME_DEPTH,
+                        0,
+                        i32::MIN + 1,
+                        i32::MAX,
+                        rng,
+                    )?
+            };
+            total_value += value as f64 * ways as f64;
+            total_ways += ways;
+            Ok(())
+        })?;
+        if is_out_of_time {
+            println!("pre-endgame out of time");
+            return Ok(false);
+        }
+        expected_values.push(total_value / total_ways.max(1) as f64);
+    }
+
+    if let Some(best) = (0..expected_values.len())
+        .max_by(|&a, &b| expected_values[a].total_cmp(&expected_values[b]))
+    {
+        println!(
+            "pre-endgame with {num_in_bag} in the bag, expected {:.1}",
+            expected_values[best]
+        );
+        move_generator.plays.swap(0, best);
+    }
+    Ok(true)
+}
@@ -0,0 +1,177 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Game reports for reviewing a finished game: an annotated GCG and a JSON
+// document with the same per-turn information.
+
+use super::macondo;
+
+use std::fmt::Write;
+
+#[derive(serde::Serialize)]
+pub struct Alternative {
+    pub play: String,
+    pub score: i32,
+    pub equity: f64,
+}
+
+#[derive(serde::Serialize)]
+pub struct TurnReport {
+    pub event_index: usize,
+    pub nickname: String,
+    pub rack: String,
+    pub played: String,
+    // 1-based, None if the played move is not in the ranked list (a phony).
+    pub played_rank: Option<usize>,
+    pub equity_loss: f64,
+    pub missed_bingo: bool,
+    pub possible_star_play: bool,
+    pub missed_star_play: bool,
+    pub top_is_bingo: bool,
+    pub alternatives: Vec<Alternative>,
+}
+
+#[derive(serde::Serialize)]
+pub struct GameReport {
+    pub uid: String,
+    pub lexicon: String,
+    pub variant: String,
+    pub players: Vec<String>,
+    pub turns: Vec<TurnReport>,
+}
+
+// how the played move is written in the report, like in a gcg.
+pub fn fmt_played(event: &macondo::GameEvent) -> String {
+    match event.r#type() {
+        macondo::game_event::Type::TilePlacementMove => {
+            format!("{} {}", event.position, event.played_tiles)
+        }
+        macondo::game_event::Type::Exchange => format!("-{}", event.exchanged),
+        _ => "-".into(),
+    }
+}
+
+// appends one gcg line for the event, without the trailing newline.
+pub fn write_gcg_event(
+    out: &mut String,
+    nickname: &str,
+    event: &macondo::GameEvent,
+) -> std::fmt::Result {
+    let rack = &event.rack;
+    let cumulative = event.cumulative;
+    match event.r#type() {
+        macondo::game_event::Type::TilePlacementMove => write!(
+            out,
+            ">{nickname}: {rack} {} {} +{} {cumulative}",
+            event.position, event.played_tiles, event.score
+        ),
+        macondo::game_event::Type::PhonyTilesReturned => write!(
+            out,
+            ">{nickname}: {rack} -- -{} {cumulative}",
+            event.lost_score
+        ),
+        macondo::game_event::Type::Pass => {
+            write!(out, ">{nickname}: {rack} - +0 {cumulative}")
+        }
+        macondo::game_event::Type::ChallengeBonus => write!(
+            out,
+            ">{nickname}: {rack} (challenge) +{} {cumulative}",
+            event.bonus
+        ),
+        macondo::game_event::Type::Exchange => write!(
+            out,
+            ">{nickname}: {rack} -{} +0 {cumulative}",
+            event.exchanged
+        ),
+        macondo::game_event::Type::EndRackPts => write!(
+            out,
+            ">{nickname}: ({rack}) +{} {cumulative}",
+            event.end_rack_points
+        ),
+        macondo::game_event::Type::TimePenalty => write!(
+            out,
+            ">{nickname}: {rack} (time) -{} {cumulative}",
+            event.lost_score
+        ),
+        macondo::game_event::Type::EndRackPenalty => write!(
+            out,
+            ">{nickname}: {rack} ({rack}) -{} {cumulative}",
+            event.lost_score
+        ),
+        macondo::game_event::Type::UnsuccessfulChallengeTurnLoss
+        | macondo::game_event::Type::Challenge => {
+            write!(out, ">{nickname}: {rack} (challenge) +0 {cumulative}")
+        }
+    }
+}
+
+fn write_gcg_note(out: &mut String, turn: &TurnReport) -> std::fmt::Result {
+    write!(out, "#note ")?;
+    match turn.played_rank {
+        Some(rank) => write!(out, "rank {rank}")?,
+        None => write!(out, "not found")?,
+    }
+    write!(out, ", equity loss {:.1}", turn.equity_loss)?;
+    if turn.missed_bingo {
+        write!(out, ", missed bingo")?;
+    }
+    if turn.missed_star_play {
+        write!(out, ", missed star play")?;
+    } else if turn.possible_star_play {
+        write!(out, ", star play")?;
+    }
+    for (i, alternative) in turn.alternatives.iter().enumerate() {
+        write!(
+            out,
+            "{} {}. {} ({:.1})",
+            if i == 0 { ";" } else { "," },
+            i + 1,
+            alternative.play,
+            alternative.equity
+        )?;
+    }
+    Ok(())
+}
+
+// player_indexes has the player of each event.
+pub fn to_gcg(
+    game_history: &macondo::GameHistory,
+    player_indexes: &[usize],
+    game_report: &GameReport,
+) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    writeln!(out, "#character-encoding UTF-8")?;
+    for (i, player) in game_history.players.iter().enumerate() {
+        writeln!(
+            out,
+            "#player{} {} {}",
+            i + 1,
+            player.nickname,
+            player.real_name
+        )?;
+    }
+    if !game_history.title.is_empty() {
+        writeln!(out, "#title {}", game_history.title)?;
+    }
+    if !game_history.uid.is_empty() {
+        writeln!(out, "#id {} {}", game_history.id_auth, game_history.uid)?;
+    }
+    writeln!(out, "#lexicon {}", game_history.lexicon)?;
+    let mut turns = game_report.turns.iter().peekable();
+    for (i, event) in game_history.events.iter().enumerate() {
+        write_gcg_event(
+            &mut out,
+            &game_history.players[player_indexes[i]].nickname,
+            event,
+        )?;
+        writeln!(out)?;
+        if let Some(turn) = turns.next_if(|turn| turn.event_index == i) {
+            write_gcg_note(&mut out, turn)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(out)
+}
+
+pub fn to_json(game_report: &GameReport) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(game_report)
+}
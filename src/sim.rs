@@ -0,0 +1,277 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Simming: each of the top candidates by equity is played out a few plies, with
+// every player making their best equity play from racks drawn from the unseen
+// tiles, and the candidates are ranked by the mean spread they lead to. With
+// more than one opponent, the spread is against the best of them. The bot sims
+// with the simmer in wolges; this one is for analysis and the tools' sim,...
+// strategies, as it draws from the rng it is given, can stop at a deadline and
+// can be tuned.
+
+use wolges::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimParams {
+    // how many of the top plays by equity are simmed.
+    pub num_candidates: usize,
+    // how many plays are made after the candidate in each rollout.
+    pub num_plies: usize,
+    // the most rollouts per candidate.
+    pub num_iterations: u32,
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        Self {
+            num_candidates: 10,
+            num_plies: 2,
+            num_iterations: 300,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SimStats {
+    pub iterations: u32,
+    // the spread gained, plus the value of the last leave if the game goes on.
+    pub mean: f64,
+    pub stdev: f64,
+    // the percentage of rollouts that end with the player ahead of everyone,
+    // ties counting half. a rollout often ends before the game does, so this
+    // is not the chance of winning.
+    pub ahead_pct: f64,
+}
+
+#[derive(Clone, Default)]
+struct Tally {
+    iterations: u32,
+    sum: f64,
+    sum_sq: f64,
+    ahead: f64,
+}
+
+impl Tally {
+    fn add(&mut self, (value, spread_after): (f64, i32)) {
+        self.iterations += 1;
+        self.sum += value;
+        self.sum_sq += value * value;
+        self.ahead += match spread_after.cmp(&0) {
+            std::cmp::Ordering::Greater => 1.0,
+            std::cmp::Ordering::Equal => 0.5,
+            std::cmp::Ordering::Less => 0.0,
+        };
+    }
+
+    fn stats(&self) -> SimStats {
+        let n = self.iterations.max(1) as f64;
+        let mean = self.sum / n;
+        SimStats {
+            iterations: self.iterations,
+            mean,
+            stdev: (self.sum_sq / n - mean * mean).max(0.0).sqrt(),
+            ahead_pct: 100.0 * self.ahead / n,
+        }
+    }
+}
+
+fn play_score(play: &movegen::Play) -> i32 {
+    match play {
+        movegen::Play::Exchange { .. } => 0,
+        movegen::Play::Place { score, .. } => equity::descale_score(*score),
+    }
+}
+
+// a pass is an exchange of no tiles.
+pub fn is_exchange(play: &movegen::Play) -> bool {
+    matches!(play, movegen::Play::Exchange { tiles } if !tiles.is_empty())
+}
+
+// me's score minus the best of the others.
+fn spread_of(me: usize, scores: impl Iterator<Item = i32>) -> i32 {
+    let mut my_score = 0;
+    let mut best_other_score = i32::MIN;
+    for (p, score) in scores.enumerate() {
+        if p == me {
+            my_score = score;
+        } else {
+            best_other_score = best_other_score.max(score);
+        }
+    }
+    my_score - best_other_score
+}
+
+struct Roller<'a, N: kwg::Node> {
+    game_config: &'a game_config::GameConfig,
+    kwg: &'a kwg::Kwg<N>,
+    klv: &'a klv::Klv<kwg::Node22>,
+    move_generator: movegen::KurniaMoveGenerator,
+    sim_state: game_state::GameState,
+    final_scores: Vec<i32>,
+}
+
+impl<N: kwg::Node> Roller<'_, N> {
+    // Plays the candidate and then num_plies best equity plays, from racks
+    // redrawn from the unseen tiles. Returns the change in spread for the
+    // player on turn in game_state, plus the value of their last leave if the
+    // game has not ended, and the spread at the end.
+    fn roll_out<R: rand::Rng>(
+        &mut self,
+        game_state: &game_state::GameState,
+        candidate: &movegen::ValuedMove,
+        num_plies: usize,
+        rng: &mut R,
+    ) -> Result<(f64, i32), Box<dyn std::error::Error>> {
+        let game_config = self.game_config;
+        let rack_size = game_config.rack_size() as usize;
+        let me = game_state.turn as usize;
+        let spread_before = spread_of(me, game_state.players.iter().map(|player| player.score));
+        let sim_state = &mut self.sim_state;
+        sim_state.clone_from(game_state);
+        sim_state.bag.shuffle(rng);
+        for (p, player) in sim_state.players.iter_mut().enumerate() {
+            while p != me
+                && player.rack.len() < rack_size
+                && let Some(tile) = sim_state.bag.0.pop()
+            {
+                player.rack.push(tile);
+            }
+        }
+        let mut play = candidate.play.clone();
+        let mut leave_value = candidate.equity.as_f64() - play_score(&play) as f64;
+        for ply in 0..=num_plies {
+            if ply > 0 {
+                let can_exchange = sim_state.bag.0.len() >= rack_size;
+                self.move_generator.gen_moves_filtered(
+                    &movegen::GenMovesParams {
+                        board_snapshot: &movegen::BoardSnapshot {
+                            board_tiles: &sim_state.board_tiles,
+                            game_config,
+                            kwg: self.kwg,
+                            klv: self.klv,
+                        },
+                        rack: &sim_state.current_player().rack,
+                        max_gen: 1,
+                        num_exchanges_by_this_player: 0,
+                        always_include_pass: true,
+                        dynamic_leaves: None,
+                    },
+                    |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+                    |leave_value: i32| leave_value,
+                    |_equity: equity::Equity, play: &movegen::Play| {
+                        can_exchange || !is_exchange(play)
+                    },
+                );
+                let best = &self.move_generator.plays[0];
+                play.clone_from(&best.play);
+                if sim_state.turn as usize == me {
+                    leave_value = best.equity.as_f64() - play_score(&play) as f64;
+                }
+            }
+            sim_state.play(game_config, rng, &play)?;
+            match sim_state.check_game_ended(game_config, &mut self.final_scores) {
+                game_state::CheckGameEnded::PlayedOut | game_state::CheckGameEnded::ZeroScores => {
+                    let spread_after = spread_of(me, self.final_scores.iter().copied());
+                    return Ok(((spread_after - spread_before) as f64, spread_after));
+                }
+                game_state::CheckGameEnded::NotEnded => {}
+            }
+            sim_state.next_turn();
+        }
+        let spread_after = spread_of(me, sim_state.players.iter().map(|player| player.score));
+        Ok((
+            (spread_after - spread_before) as f64 + leave_value,
+            spread_after,
+        ))
+    }
+}
+
+// How many unseen tiles are really in the bag. game_state.bag also holds the
+// tiles on the other players' racks that are not known.
+pub fn num_tiles_in_bag(game_state: &game_state::GameState, rack_size: usize) -> usize {
+    let me = game_state.turn as usize;
+    let num_unknown_on_racks = (0..game_state.players.len())
+        .filter(|&p| p != me)
+        .map(|p| rack_size.saturating_sub(game_state.players[p].rack.len()))
+        .sum::<usize>();
+    game_state.bag.0.len().saturating_sub(num_unknown_on_racks)
+}
+
+// Puts everyone else's rack back in the bag, for a game state where the other
+// racks are known but the player on turn should not see them.
+pub fn unsee_other_racks(game_state: &mut game_state::GameState) {
+    let me = game_state.turn as usize;
+    for (p, player) in game_state.players.iter_mut().enumerate() {
+        if p != me {
+            game_state.bag.0.append(&mut player.rack);
+        }
+    }
+}
+
+// Leaves the candidates in move_generator.plays, the best first, and returns
+// their stats in the same order. game_state is for the player on turn, with
+// every unseen tile in the bag. Every candidate is rolled out the same number
+// of times, stopping at the deadline once each has been rolled out once.
+#[expect(clippy::too_many_arguments)]
+pub fn pick_a_move<N: kwg::Node, R: rand::Rng>(
+    params: &SimParams,
+    game_state: &game_state::GameState,
+    game_config: &game_config::GameConfig,
+    kwg: &kwg::Kwg<N>,
+    klv: &klv::Klv<kwg::Node22>,
+    move_generator: &mut movegen::KurniaMoveGenerator,
+    option_deadline: Option<std::time::Instant>,
+    rng: &mut R,
+) -> Result<Vec<SimStats>, Box<dyn std::error::Error>> {
+    let rack_size = game_config.rack_size() as usize;
+    let can_exchange = num_tiles_in_bag(game_state, rack_size) >= rack_size;
+    move_generator.gen_moves_filtered(
+        &movegen::GenMovesParams {
+            board_snapshot: &movegen::BoardSnapshot {
+                board_tiles: &game_state.board_tiles,
+                game_config,
+                kwg,
+                klv,
+            },
+            rack: &game_state.current_player().rack,
+            max_gen: params.num_candidates,
+            num_exchanges_by_this_player: 0,
+            always_include_pass: true,
+            dynamic_leaves: None,
+        },
+        |_down: bool, _lane: i8, _idx: i8, _word: &[u8], _score: i32| true,
+        |leave_value: i32| leave_value,
+        |_equity: equity::Equity, play: &movegen::Play| can_exchange || !is_exchange(play),
+    );
+    let mut tallies = vec![Tally::default(); move_generator.plays.len()];
+    // a forced play needs no simming.
+    if tallies.len() > 1 {
+        let mut roller = Roller {
+            game_config,
+            kwg,
+            klv,
+            move_generator: movegen::KurniaMoveGenerator::new(game_config),
+            sim_state: game_state.clone(),
+            final_scores: vec![0; game_state.players.len()],
+        };
+        for iteration in 0..params.num_iterations {
+            if iteration > 0
+                && option_deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline)
+            {
+                break;
+            }
+            for (candidate, tally) in move_generator.plays.iter().zip(tallies.iter_mut()) {
+                tally.add(roller.roll_out(game_state, candidate, params.num_plies, rng)?);
+            }
+        }
+    }
+    let mut ranked = std::mem::take(&mut move_generator.plays)
+        .into_iter()
+        .zip(tallies.iter().map(Tally::stats))
+        .collect::<Vec<_>>();
+    if ranked.len() > 1 {
+        ranked.sort_by(|a, b| b.1.mean.total_cmp(&a.1.mean));
+    }
+    let stats;
+    (move_generator.plays, stats) = ranked.into_iter().unzip();
+    Ok(stats)
+}
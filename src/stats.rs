@@ -0,0 +1,29 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Summary statistics for matches between bots.
+
+// 1 for a win, 0.5 for a draw, 0 for a loss.
+pub fn win_share(my_score: i32, your_score: i32) -> f64 {
+    match my_score.cmp(&your_score) {
+        std::cmp::Ordering::Greater => 1.0,
+        std::cmp::Ordering::Equal => 0.5,
+        std::cmp::Ordering::Less => 0.0,
+    }
+}
+
+// mean and standard error.
+pub fn mean_and_stderr(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0).max(1.0);
+    (mean, (variance / n).sqrt())
+}
+
+// the rating difference that makes the stronger side score this share.
+pub fn elo_of(score_share: f64) -> f64 {
+    let p = score_share.clamp(0.001, 0.999);
+    -400.0 * (1.0 / p - 1.0).log10()
+}
+
+// for 95% confidence intervals.
+pub const Z_95: f64 = 1.96;
@@ -0,0 +1,75 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// How a bot picks its moves, as named on the command line of the tools: hasty,
+// noleave, sim, or tilt1 to tilt5, optionally followed by +common to play only
+// common words. sim is the simmer the bot uses. With settings, like
+// sim,candidates=20,plies=3,iterations=500, it is the rollouts in sim.rs with
+// those settings changed from their defaults instead.
+
+use super::sim;
+
+use std::str::FromStr;
+
+#[derive(Clone, Copy)]
+pub enum Picker {
+    Hasty,
+    NoLeave,
+    Tilt(i8),
+    // None for the bot's simmer.
+    Sim(Option<sim::SimParams>),
+}
+
+#[derive(Clone, Copy)]
+pub struct Strategy {
+    pub picker: Picker,
+    pub use_common_word: bool,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (picker_str, use_common_word) = match s.strip_suffix("+common") {
+            Some(picker_str) => (picker_str, true),
+            None => (s, false),
+        };
+        let bad_strategy = || format!("bad strategy {s:?}");
+        let picker = match picker_str.split_once(',') {
+            Some(("sim", settings)) => {
+                Picker::Sim(Some(sim_params(settings).ok_or_else(bad_strategy)?))
+            }
+            Some(_) => return Err(bad_strategy()),
+            None => match picker_str {
+                "hasty" => Picker::Hasty,
+                "noleave" => Picker::NoLeave,
+                "sim" => Picker::Sim(None),
+                _ => match picker_str.strip_prefix("tilt").map(i8::from_str) {
+                    Some(Ok(bot_level)) if (1..=5).contains(&bot_level) => Picker::Tilt(bot_level),
+                    _ => return Err(bad_strategy()),
+                },
+            },
+        };
+        Ok(Self {
+            picker,
+            use_common_word,
+        })
+    }
+}
+
+// The defaults with the comma-separated settings applied. None if any is bad.
+fn sim_params(settings: &str) -> Option<sim::SimParams> {
+    let mut sim_params = sim::SimParams::default();
+    for setting in settings.split(',') {
+        match setting.split_once('=')? {
+            ("candidates", value) => {
+                sim_params.num_candidates = value.parse().ok().filter(|&n| n > 0)?
+            }
+            ("plies", value) => sim_params.num_plies = value.parse().ok()?,
+            ("iterations", value) => {
+                sim_params.num_iterations = value.parse().ok().filter(|&n| n > 0)?
+            }
+            _ => return None,
+        }
+    }
+    Some(sim_params)
+}
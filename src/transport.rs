@@ -0,0 +1,224 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Where bot requests come from, and where the responses go.
+
+use super::{analysis, macondo};
+
+use futures_util::StreamExt;
+use prost::Message;
+
+pub type RequestResult = Result<macondo::BotRequest, Box<dyn std::error::Error + Send + Sync>>;
+
+// Answers one request.
+pub trait Responder: Send + 'static {
+    // whether the request came in for the ranked candidates instead of a move.
+    fn wants_analysis(&self) -> bool;
+
+    // whether the client is waiting for a reply even when the bot has nothing
+    // to say, as an HTTP client is. otherwise such requests go unanswered.
+    fn must_reply(&self) -> bool {
+        false
+    }
+
+    fn send(
+        self,
+        bot_resp: macondo::BotResponse,
+    ) -> impl Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send;
+
+    fn send_analysis(
+        self,
+        analysis_resp: analysis::AnalysisResponse,
+    ) -> impl Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send;
+}
+
+pub trait Transport {
+    type Responder: Responder;
+
+    // None when no more requests will come. A request that cannot be decoded
+    // still comes with a responder, so the error can be reported.
+    fn receive(&mut self) -> impl Future<Output = Option<(RequestResult, Self::Responder)>>;
+}
+
+pub struct NatsTransport {
+    nc: std::sync::Arc<async_nats::Client>,
+    sub: async_nats::Subscriber,
+    analysis_sub: async_nats::Subscriber,
+}
+
+impl NatsTransport {
+    pub async fn connect(nats_url: String) -> Result<Self, Box<dyn std::error::Error>> {
+        let nc = std::sync::Arc::new(async_nats::connect(nats_url).await?);
+        let sub = nc
+            .queue_subscribe("bot.commands".to_string(), "bot_queue".to_string())
+            .await?;
+        let analysis_sub = nc
+            .queue_subscribe("bot.analyze".to_string(), "bot_queue".to_string())
+            .await?;
+        Ok(Self {
+            nc,
+            sub,
+            analysis_sub,
+        })
+    }
+}
+
+impl Transport for NatsTransport {
+    type Responder = NatsResponder;
+
+    async fn receive(&mut self) -> Option<(RequestResult, NatsResponder)> {
+        let (msg, is_analysis) = tokio::select! {
+            Some(msg) = self.sub.next() => (msg, false),
+            Some(msg) = self.analysis_sub.next() => (msg, true),
+            else => return None,
+        };
+        let bot_req = macondo::BotRequest::decode(&*msg.payload);
+        // When the request came in via NATS request/reply (as the analysis tool
+        // does), answer on the reply inbox; otherwise fall back to publishing on
+        // the per-game channel liwords listens on. Analyses only go to the inbox.
+        let option_subject = msg.reply.or_else(|| {
+            bot_req
+                .as_ref()
+                .ok()
+                .filter(|_| !is_analysis)
+                .and_then(|bot_req| bot_req.game_history.as_ref())
+                .map(|game_history| format!("bot.publish_event.{}", game_history.uid).into())
+        });
+        Some((
+            bot_req.map_err(|err| err.into()),
+            NatsResponder {
+                nc: std::sync::Arc::clone(&self.nc),
+                option_subject,
+                is_analysis,
+            },
+        ))
+    }
+}
+
+pub struct NatsResponder {
+    nc: std::sync::Arc<async_nats::Client>,
+    // None if there is nowhere to answer.
+    option_subject: Option<async_nats::Subject>,
+    is_analysis: bool,
+}
+
+impl NatsResponder {
+    async fn publish<M: Message + Send>(
+        self,
+        msg: M,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut buf = Vec::new();
+        msg.encode(&mut buf)?;
+        println!("{buf:?}");
+        if let Some(subject) = self.option_subject {
+            self.nc.publish(subject, buf.into()).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Responder for NatsResponder {
+    fn wants_analysis(&self) -> bool {
+        self.is_analysis
+    }
+
+    async fn send(
+        self,
+        bot_resp: macondo::BotResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.publish(bot_resp).await
+    }
+
+    async fn send_analysis(
+        self,
+        analysis_resp: analysis::AnalysisResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.publish(analysis_resp).await
+    }
+}
+
+// Serves requests sent through the paired InMemoryClient, without a broker.
+pub struct InMemoryTransport {
+    requests: tokio::sync::mpsc::UnboundedReceiver<macondo::BotRequest>,
+    responses: tokio::sync::mpsc::UnboundedSender<macondo::BotResponse>,
+    analysis_requests: tokio::sync::mpsc::UnboundedReceiver<macondo::BotRequest>,
+    analyses: tokio::sync::mpsc::UnboundedSender<analysis::AnalysisResponse>,
+}
+
+// Responses come back in the order they are ready, which is not necessarily
+// the order of the requests. BotResponse::game_id tells them apart.
+pub struct InMemoryClient {
+    pub requests: tokio::sync::mpsc::UnboundedSender<macondo::BotRequest>,
+    pub responses: tokio::sync::mpsc::UnboundedReceiver<macondo::BotResponse>,
+    pub analysis_requests: tokio::sync::mpsc::UnboundedSender<macondo::BotRequest>,
+    pub analyses: tokio::sync::mpsc::UnboundedReceiver<analysis::AnalysisResponse>,
+}
+
+pub fn in_memory() -> (InMemoryTransport, InMemoryClient) {
+    let (requests_tx, requests_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (responses_tx, responses_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (analysis_requests_tx, analysis_requests_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (analyses_tx, analyses_rx) = tokio::sync::mpsc::unbounded_channel();
+    (
+        InMemoryTransport {
+            requests: requests_rx,
+            responses: responses_tx,
+            analysis_requests: analysis_requests_rx,
+            analyses: analyses_tx,
+        },
+        InMemoryClient {
+            requests: requests_tx,
+            responses: responses_rx,
+            analysis_requests: analysis_requests_tx,
+            analyses: analyses_rx,
+        },
+    )
+}
+
+impl Transport for InMemoryTransport {
+    type Responder = InMemoryResponder;
+
+    async fn receive(&mut self) -> Option<(RequestResult, InMemoryResponder)> {
+        let (bot_req, option_analyses) = tokio::select! {
+            Some(bot_req) = self.requests.recv() => (bot_req, None),
+            Some(bot_req) = self.analysis_requests.recv() => (bot_req, Some(self.analyses.clone())),
+            else => return None,
+        };
+        Some((
+            Ok(bot_req),
+            InMemoryResponder {
+                responses: self.responses.clone(),
+                option_analyses,
+            },
+        ))
+    }
+}
+
+pub struct InMemoryResponder {
+    responses: tokio::sync::mpsc::UnboundedSender<macondo::BotResponse>,
+    // Some if the request came in for an analysis.
+    option_analyses: Option<tokio::sync::mpsc::UnboundedSender<analysis::AnalysisResponse>>,
+}
+
+impl Responder for InMemoryResponder {
+    fn wants_analysis(&self) -> bool {
+        self.option_analyses.is_some()
+    }
+
+    async fn send(
+        self,
+        bot_resp: macondo::BotResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.responses.send(bot_resp)?;
+        Ok(())
+    }
+
+    async fn send_analysis(
+        self,
+        analysis_resp: analysis::AnalysisResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.option_analyses
+            .ok_or("not an analysis request")?
+            .send(analysis_resp)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright (C) 2020-2026 Andy Kurnia.
+
+// Sends bot requests through the in-memory transport to a service that knows
+// only a tiny CSW24 of four words.
+
+use omgbot::{macondo, service, transport};
+use wolges::*;
+
+// Each test loads from its own directory, as tests run in parallel.
+fn tiny_lexicons(test_name: &str) -> Result<service::Lexicons, Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("omgbot-{test_name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    // A=1, C=3, T=20.
+    let mut sorted_words: [&[u8]; 4] = [&[1, 3, 20], &[1, 20], &[3, 1, 20], &[20, 1]];
+    sorted_words.sort_unstable();
+    let words = sorted_words
+        .iter()
+        .map(|&word| word.into())
+        .collect::<Vec<bites::Bites>>();
+    std::fs::write(
+        dir.join("CSW24.kwg"),
+        build::build(
+            build::BuildContent::Gaddawg,
+            build::BuildLayout::Wolges,
+            &words,
+        )?,
+    )?;
+    std::fs::write(dir.join("CSW24.klv2"), klv::EMPTY_KLV_BYTES)?;
+    let lexicons = service::Lexicons::load(&dir);
+    std::fs::remove_dir_all(&dir)?;
+    lexicons
+}
+
+fn first_move_request(lexicon: &str) -> macondo::BotRequest {
+    macondo::BotRequest {
+        game_history: Some(macondo::GameHistory {
+            players: vec![Default::default(); 2],
+            lexicon: lexicon.into(),
+            uid: "in-memory".into(),
+            last_known_racks: vec!["CATQZJK".into(), "".into()],
+            ..Default::default()
+        }),
+        bot_type: macondo::bot_request::BotCode::HastyBot.into(),
+        ..Default::default()
+    }
+}
+
+// Sends one request and waits for its response while the service runs.
+async fn ask(
+    lexicons: &service::Lexicons,
+    bot_req: macondo::BotRequest,
+) -> Result<macondo::BotResponse, Box<dyn std::error::Error>> {
+    let settings = service::Settings::from_env()?;
+    let (mut in_memory_transport, mut client) = transport::in_memory();
+    client.requests.send(bot_req)?;
+    tokio::select! {
+        _ = service::serve(&mut in_memory_transport, lexicons, &settings) => {
+            Err("the service stopped without responding".into())
+        }
+        option_bot_resp = client.responses.recv() => {
+            Ok(option_bot_resp.ok_or("no response")?)
+        }
+    }
+}
+
+#[tokio::test]
+async fn plays_the_best_first_move() -> Result<(), Box<dyn std::error::Error>> {
+    let lexicons = tiny_lexicons("plays_the_best_first_move")?;
+    let bot_resp = ask(&lexicons, first_move_request("CSW24")).await?;
+    assert_eq!(bot_resp.game_id, "in-memory");
+    let Some(macondo::bot_response::Response::Move(game_event)) = &bot_resp.response else {
+        panic!("expected a move, got {bot_resp:?}");
+    };
+    assert_eq!(
+        game_event.r#type(),
+        macondo::game_event::Type::TilePlacementMove
+    );
+    // CAT and ACT both score 10.
+    let mut letters = game_event.played_tiles.chars().collect::<Vec<_>>();
+    letters.sort_unstable();
+    assert_eq!(letters, ['A', 'C', 'T']);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reports_an_unknown_lexicon() -> Result<(), Box<dyn std::error::Error>> {
+    let lexicons = tiny_lexicons("reports_an_unknown_lexicon")?;
+    let bot_resp = ask(&lexicons, first_move_request("NWL23")).await?;
+    assert_eq!(
+        bot_resp.response,
+        Some(macondo::bot_response::Response::Error(
+            "not familiar with the lexicon".into()
+        ))
+    );
+    Ok(())
+}